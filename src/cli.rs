@@ -0,0 +1,100 @@
+// Non-interactive commands, so references can be made from scripts.
+
+use std::{path::Path, slice};
+
+use apa::{ApaFormat, ApaFormatType, Lang, output::OutputFormat};
+
+fn usage() -> String {
+    let outputs: Vec<&str> = OutputFormat::list().iter().map(OutputFormat::name).collect();
+    let formats: Vec<&str> = ApaFormatType::list().iter().map(ApaFormatType::name).collect();
+    format!("usage:
+  apa                                            open the interactive editor
+  apa export <output> <format> [field=value]...  print a reference
+      -o <file>                                  save it to a file instead
+
+outputs: {}
+formats: {}
+add --es to use spanish dates", outputs.join(", "), formats.join(", "))
+}
+
+// Run the command in the arguments, returns the exit code of the program.
+pub fn run(args: &[String]) -> i32 {
+    match args.first().map(String::as_str) {
+        Some("export") => export(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", usage());
+            0
+        }
+        _ => usage_error("unknown command"),
+    }
+}
+
+fn export(args: &[String]) -> i32 {
+    let (output, format) = match args {
+        [output, format, ..] => (output, format),
+        _ => return usage_error("export needs an output and a format"),
+    };
+    let output = match OutputFormat::from_name(output) {
+        Some(output) => output,
+        None => return usage_error(&format!("unknown output \"{}\"", output)),
+    };
+    let format = match ApaFormatType::from_name(format) {
+        Some(format) => format,
+        None => return usage_error(&format!("unknown format \"{}\"", format)),
+    };
+
+    let lang = if args.iter().any(|arg| arg == "--es") { Lang::Spanish } else { Lang::English };
+    let mut apa = ApaFormat::new(format, Some(lang));
+
+    // Where to save it, documents can't go to the terminal.
+    let mut path = None;
+    let mut rest = args[2..].iter();
+    let mut fields = Vec::new();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--es" => {}
+            "-o" => match rest.next() {
+                Some(file) => path = Some(file.clone()),
+                None => return usage_error("-o needs a file"),
+            },
+            _ => fields.push(arg),
+        }
+    }
+    if path.is_none() && output.is_binary() {
+        return usage_error(&format!("{} can only be saved to a file, use -o", output.name()));
+    }
+
+    for arg in fields {
+        let (field, value) = match arg.split_once('=') {
+            Some(pair) => pair,
+            None => return usage_error(&format!("\"{}\" should be field=value", arg)),
+        };
+        if !apa.set(field, value) {
+            let fields: Vec<&str> = apa.fields().iter().map(|field| field.id).collect();
+            return usage_error(&format!("{} has no field \"{}\" (fields: {})", format, field, fields.join(", ")));
+        }
+    }
+
+    // Same warnings the editor shows, on stderr so they don't end up in the output.
+    for (field, warning) in apa.fields().iter().zip(apa.validate()) {
+        if let Some(warning) = warning {
+            eprintln!("warning: {}: {}", field.id, warning);
+        }
+    }
+
+    match path {
+        Some(path) => {
+            if let Err(error) = output.save(Path::new(&path), slice::from_ref(&apa)) {
+                eprintln!("apa: couldn't save {}: {}", path, error);
+                return 1;
+            }
+        }
+        None => println!("{}", output.render(&apa)),
+    }
+    0
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("apa: {}\n\n{}", message, usage());
+    2
+}
@@ -0,0 +1,279 @@
+// arXiv preprints by their id, from the Atom feed of the arXiv API or a server with the same /api/query.
+
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use crate::date::PublicationDate;
+use crate::doi::Doi;
+use crate::fetch::{join, FetchError, HttpClient};
+use crate::person::{format_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// "2001.01234v2" since 2007, "hep-th/9901001" before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArxivId {
+    pub id: String,
+    /// None cites the latest version.
+    pub version: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArxivIdError {
+    Empty,
+    Invalid(String),
+}
+
+impl fmt::Display for ArxivIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "arXiv ID is empty"),
+            Self::Invalid(text) => write!(f, "\"{}\" is not an arXiv ID like 2001.01234 or hep-th/9901001", text),
+        }
+    }
+}
+
+impl ArxivId {
+    // Also from the links to the abstract or the PDF, and from arXiv DOIs.
+    pub fn parse(input: &str) -> Result<ArxivId, ArxivIdError> {
+        let mut text = input.trim().trim_end_matches('/');
+        if text.is_empty() {
+            return Err(ArxivIdError::Empty);
+        }
+        let lower = text.to_lowercase();
+        for marker in ["/abs/", "/pdf/", "10.48550/arxiv.", "arxiv:"] {
+            if let Some(start) = lower.find(marker) {
+                text = &text[start + marker.len()..];
+                break;
+            }
+        }
+        let text = text.trim_end_matches(".pdf");
+
+        // The version is at the end, "v2".
+        let (id, version) = match text.rfind('v') {
+            Some(v) if text[v + 1..].parse::<u32>().is_ok() && v > 0 => (&text[..v], text[v + 1..].parse().ok()),
+            _ => (text, None),
+        };
+        if is_new_style(id) || is_old_style(id) {
+            Ok(ArxivId { id: id.to_string(), version })
+        } else {
+            Err(ArxivIdError::Invalid(input.trim().to_string()))
+        }
+    }
+
+    // Where the version that is cited can be read.
+    pub fn url(&self) -> String {
+        format!("https://arxiv.org/abs/{}", self)
+    }
+}
+
+impl fmt::Display for ArxivId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{}v{}", self.id, version),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+// YYMM.NNNN, or YYMM.NNNNN since 2015.
+fn is_new_style(id: &str) -> bool {
+    let Some((date, number)) = id.split_once('.') else {
+        return false;
+    };
+    let digits = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+    let month = date.get(2..).and_then(|month| month.parse::<u32>().ok()).unwrap_or(0);
+    date.len() == 4 && digits(date) && (1..=12).contains(&month) && (4..=5).contains(&number.len()) && digits(number)
+}
+
+// archive.SUBJECT/YYMMNNN, like math.GT/0309136.
+fn is_old_style(id: &str) -> bool {
+    let Some((archive, number)) = id.split_once('/') else {
+        return false;
+    };
+    let (archive, subject) = archive.split_once('.').unwrap_or((archive, "AA"));
+    !archive.is_empty()
+        && archive.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+        && subject.len() == 2
+        && subject.chars().all(|c| c.is_ascii_uppercase())
+        && number.len() == 7
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+// The preprint, with warnings, and the DOI of the published article if the authors added it.
+pub fn lookup(client: &dyn HttpClient, base_url: &str, id: &ArxivId, lang: Lang) -> Result<(ApaFormat, Vec<String>, Option<Doi>), FetchError> {
+    let url = join(base_url, &format!("api/query?id_list={}", id));
+    let body = client.get(&url, "application/atom+xml")?;
+    let document = Document::parse(&body).map_err(|error| FetchError::Invalid(error.to_string()))?;
+    // Unknown ids give no entry, or an entry about the error.
+    let entry = document.descendants()
+        .find(|node| node.tag_name().name() == "entry")
+        .filter(|entry| !text(entry, "id").contains("/api/errors"))
+        .ok_or(FetchError::NotFound)?;
+    Ok(from_entry(entry, id, lang))
+}
+
+pub fn from_entry(entry: Node, id: &ArxivId, lang: Lang) -> (ApaFormat, Vec<String>, Option<Doi>) {
+    let mut apa = ApaFormat::new(ApaFormatType::Preprint, Some(lang));
+    let mut warnings = Vec::new();
+
+    let authors: Vec<Person> = entry.children()
+        .filter(|node| node.tag_name().name() == "author")
+        .map(|author| text(&author, "name"))
+        .filter(|name| !name.is_empty())
+        .map(|name| Person::from_full_name(&name))
+        .collect();
+    apa.set("authors", format_people(&authors));
+
+    // "updated" is when this version was submitted, "published" the first one.
+    let date = [text(&entry, "updated"), text(&entry, "published")].into_iter()
+        .find_map(|date| PublicationDate::parse(date.split('T').next().unwrap_or("")));
+    if let Some(date) = date {
+        apa.set_date(&date);
+    }
+
+    apa.set("title", text(&entry, "title"));
+    apa.set("archive", "arXiv");
+
+    // The entry's id is the link of the version it describes.
+    let version_url = match ArxivId::parse(&text(&entry, "id")) {
+        Ok(found) if found.version.is_some() => found.url(),
+        _ => id.url(),
+    };
+    apa.set("url", version_url);
+
+    let published = Doi::parse(&text(&entry, "doi")).ok();
+    if published.is_none() {
+        let journal = text(&entry, "journal_ref");
+        if !journal.is_empty() {
+            warnings.push(format!("it was published in {}, cite that version if you read it", journal));
+        }
+    }
+
+    (apa, warnings, published)
+}
+
+// The text of the first child with that name, whatever its namespace.
+fn text(node: &Node, name: &str) -> String {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .and_then(|child| child.text())
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FakeClient;
+
+    fn id(id: &str, version: Option<u32>) -> ArxivId {
+        ArxivId { id: id.to_string(), version }
+    }
+
+    #[test]
+    fn new_style_ids() {
+        assert_eq!(ArxivId::parse("1706.03762"), Ok(id("1706.03762", None)));
+        assert_eq!(ArxivId::parse(" 2001.01234v2 "), Ok(id("2001.01234", Some(2))));
+        assert_eq!(ArxivId::parse("arXiv:1706.03762v7"), Ok(id("1706.03762", Some(7))));
+        assert_eq!(ArxivId::parse("0704.0001"), Ok(id("0704.0001", None)));
+    }
+
+    #[test]
+    fn old_style_ids() {
+        assert_eq!(ArxivId::parse("hep-th/9901001"), Ok(id("hep-th/9901001", None)));
+        assert_eq!(ArxivId::parse("math.GT/0309136v1"), Ok(id("math.GT/0309136", Some(1))));
+    }
+
+    #[test]
+    fn links_and_dois() {
+        assert_eq!(ArxivId::parse("https://arxiv.org/abs/1706.03762v5"), Ok(id("1706.03762", Some(5))));
+        assert_eq!(ArxivId::parse("https://arxiv.org/pdf/1706.03762.pdf"), Ok(id("1706.03762", None)));
+        assert_eq!(ArxivId::parse("http://arxiv.org/abs/hep-th/9711200v3/"), Ok(id("hep-th/9711200", Some(3))));
+        assert_eq!(ArxivId::parse("https://doi.org/10.48550/arXiv.1706.03762"), Ok(id("1706.03762", None)));
+        assert_eq!(id("1706.03762", Some(5)).url(), "https://arxiv.org/abs/1706.03762v5");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(ArxivId::parse("  "), Err(ArxivIdError::Empty));
+        // There is no month 13.
+        assert!(ArxivId::parse("1713.03762").is_err());
+        assert!(ArxivId::parse("1706.123").is_err());
+        assert!(ArxivId::parse("HEP-TH/9901001").is_err());
+        assert_eq!(
+            ArxivId::parse("10.1000/xyz").unwrap_err().to_string(),
+            "\"10.1000/xyz\" is not an arXiv ID like 2001.01234 or hep-th/9901001",
+        );
+    }
+
+    const BASE: &str = "https://export.arxiv.org";
+
+    fn client() -> FakeClient {
+        FakeClient::new(&[
+            ("https://export.arxiv.org/api/query?id_list=1706.03762", include_str!("../../tests/fixtures/arxiv/attention.atom")),
+            ("https://export.arxiv.org/api/query?id_list=hep-th/9711200", include_str!("../../tests/fixtures/arxiv/maldacena.atom")),
+            ("https://export.arxiv.org/api/query?id_list=2001.99999", include_str!("../../tests/fixtures/arxiv/error.atom")),
+            ("https://export.arxiv.org/api/query?id_list=2001.00001", include_str!("../../tests/fixtures/arxiv/empty.atom")),
+        ])
+    }
+
+    fn lookup_id(text: &str) -> Result<(ApaFormat, Vec<String>, Option<Doi>), FetchError> {
+        lookup(&client(), BASE, &ArxivId::parse(text).unwrap(), Lang::English)
+    }
+
+    #[test]
+    fn preprints() {
+        let (apa, warnings, published) = lookup_id("1706.03762").unwrap();
+        assert!(warnings.is_empty());
+        assert!(published.is_none());
+        assert!(apa.format == ApaFormatType::Preprint);
+        assert_eq!(apa.get("authors"), "Vaswani, A., Shazeer, N., & Parmar, N.");
+        // The date of the version that is cited.
+        assert_eq!(apa.get("date"), "2023");
+        assert_eq!(apa.get("title"), "Attention Is All You Need");
+        assert_eq!(apa.get("archive"), "arXiv");
+        assert_eq!(apa.get("url"), "https://arxiv.org/abs/1706.03762v7");
+    }
+
+    #[test]
+    fn published_versions() {
+        let (apa, warnings, published) = lookup_id("hep-th/9711200").unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(published.unwrap().to_string(), "10.1023/A:1026654312961");
+        assert_eq!(apa.get("authors"), "Maldacena, J. M.");
+        assert_eq!(apa.get("date"), "1998");
+        assert_eq!(apa.get("url"), "https://arxiv.org/abs/hep-th/9711200v3");
+    }
+
+    #[test]
+    fn journal_references_without_a_doi() {
+        let feed = r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:arxiv="http://arxiv.org/schemas/atom">
+            <entry>
+                <id>http://arxiv.org/abs/1512.03385v1</id>
+                <published>2015-12-10T19:51:55Z</published>
+                <title>Deep Residual Learning
+                    for Image Recognition</title>
+                <author><name>Kaiming He</name></author>
+                <arxiv:journal_ref>CVPR 2016</arxiv:journal_ref>
+            </entry>
+        </feed>"#;
+        let document = Document::parse(feed).unwrap();
+        let entry = document.descendants().find(|node| node.has_tag_name(("http://www.w3.org/2005/Atom", "entry"))).unwrap();
+        let (apa, warnings, published) = from_entry(entry, &id("1512.03385", None), Lang::English);
+        assert!(published.is_none());
+        assert_eq!(warnings, ["it was published in CVPR 2016, cite that version if you read it"]);
+        assert_eq!(apa.get("title"), "Deep Residual Learning for Image Recognition");
+        assert_eq!(apa.get("date"), "2015");
+        assert_eq!(apa.get("url"), "https://arxiv.org/abs/1512.03385v1");
+    }
+
+    #[test]
+    fn unknown_ids() {
+        assert!(matches!(lookup_id("2001.99999"), Err(FetchError::NotFound)));
+        assert!(matches!(lookup_id("2001.00001"), Err(FetchError::NotFound)));
+        assert!(matches!(lookup_id("2002.00001"), Err(FetchError::NotFound)));
+    }
+}
@@ -0,0 +1,357 @@
+// BibLaTeX entries that biblatex-apa renders like the references of this program.
+
+use std::collections::HashMap;
+
+use crate::bibtex::protect_capitals;
+use crate::date::PublicationDate;
+use crate::locator::PageLocator;
+use crate::person::{parse_people, Person};
+use crate::{ApaFormat, ApaFormatType};
+
+// @type of each format.
+pub fn entry_type(format: ApaFormatType) -> &'static str {
+    match format {
+        ApaFormatType::Website => "online",
+        // biblatex-apa tells newspapers apart with the entrysubtype.
+        ApaFormatType::Newspaper => "article",
+        ApaFormatType::Dictionary => "inreference",
+        ApaFormatType::Journal => "article",
+        ApaFormatType::Chapter => "incollection",
+        ApaFormatType::Book => "book",
+        ApaFormatType::Preprint => "online",
+        ApaFormatType::None => "misc",
+    }
+}
+
+// The fields of the entry, in the order they are written. Values are
+// already escaped, empty fields are left out.
+pub fn fields(apa: &ApaFormat) -> Vec<(&'static str, String)> {
+    let mut fields: Vec<(&'static str, String)> = Vec::new();
+
+    fields.push(("author", names(&parse_people(apa.get("authors")))));
+    match apa.format {
+        ApaFormatType::Dictionary => {
+            fields.push(("title", title(apa.get("word"))));
+            fields.push(("booktitle", title(apa.get("dictionary"))));
+            fields.push(("editor", names(&parse_people(apa.get("editors")))));
+            fields.push(("publisher", escape_latex(apa.get("publisher"))));
+        }
+        ApaFormatType::Chapter => {
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("booktitle", title(apa.get("book"))));
+            fields.push(("editor", names(&parse_people(apa.get("editors")))));
+            fields.push(("publisher", escape_latex(apa.get("publisher"))));
+        }
+        ApaFormatType::Journal => {
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("journaltitle", title(apa.get("journal"))));
+            fields.push(("volume", escape_latex(apa.get("volume"))));
+            fields.push(("number", escape_latex(apa.get("issue"))));
+        }
+        ApaFormatType::Newspaper => {
+            fields.push(("entrysubtype", "newspaper".to_string()));
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("journaltitle", title(apa.get("newspaper"))));
+        }
+        ApaFormatType::Website => {
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("organization", escape_latex(apa.get("website"))));
+        }
+        ApaFormatType::Book => {
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("edition", escape_latex(apa.get("edition"))));
+            fields.push(("publisher", escape_latex(apa.get("publisher"))));
+        }
+        ApaFormatType::Preprint => {
+            fields.push(("title", title(apa.get("title"))));
+            fields.push(("pubstate", "preprint".to_string()));
+            fields.push(("organization", escape_latex(apa.get("archive"))));
+        }
+        ApaFormatType::None => {}
+    }
+
+    // Pages, or the article number of online journals.
+    match PageLocator::parse(apa.get("pages")) {
+        Some((PageLocator::Single(page), _)) => fields.push(("pages", escape_latex(&page))),
+        Some((PageLocator::Range(start, end), _)) => fields.push(("pages", format!("{}--{}", escape_latex(&start), escape_latex(&end)))),
+        Some((PageLocator::Article(number), _)) => fields.push(("eid", escape_latex(&number))),
+        None => {}
+    }
+
+    if let Some(date) = PublicationDate::parse(apa.get("date")) {
+        fields.push(("date", date.iso()));
+    }
+
+    // Links are read verbatim, they don't need escaping.
+    if let Some(doi) = apa.doi() {
+        fields.push(("doi", doi.to_string()));
+    } else if !apa.get("url").is_empty() {
+        fields.push(("url", apa.url()));
+        if retrieved(apa.format) {
+            fields.push(("urldate", apa.retrieved().iso()));
+        }
+    }
+
+    fields.retain(|(_, value)| !value.is_empty());
+    fields
+}
+
+// biblatex-apa writes titles in sentence case, capitals of names and acronyms are braced.
+fn title(text: &str) -> String {
+    protect_capitals(&escape_latex(text))
+}
+
+// Formats whose content can change, so the date they were consulted matters.
+fn retrieved(format: ApaFormatType) -> bool {
+    matches!(format, ApaFormatType::Website | ApaFormatType::Newspaper | ApaFormatType::Dictionary)
+}
+
+pub fn entry(apa: &ApaFormat, key: &str) -> String {
+    write_entry(entry_type(apa.format), key, &fields(apa))
+}
+
+// Write a @type{key, field = {value}} block, shared with BibTeX.
+pub fn write_entry(entry_type: &str, key: &str, fields: &[(&str, String)]) -> String {
+    write_entry_with_macros(entry_type, key, fields, &[])
+}
+
+// The same block, with the values of the macro fields written bare, month = jan.
+pub fn write_entry_with_macros(entry_type: &str, key: &str, fields: &[(&str, String)], macros: &[&str]) -> String {
+    let mut output = format!("@{}{{{},\n", entry_type, key);
+    for (name, value) in fields {
+        if macros.contains(name) {
+            output.push_str(&format!("  {} = {},\n", name, value));
+        } else {
+            output.push_str(&format!("  {} = {{{}}},\n", name, value));
+        }
+    }
+    output.push_str("}\n");
+    output
+}
+
+// Every reference with its own key, in the order they were given.
+pub fn bibliography(references: &[ApaFormat]) -> String {
+    unique_keys(references).iter()
+        .zip(references)
+        .map(|(key, apa)| entry(apa, key))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// "smith2020memory", from the first author, the year and the first important word of the title.
+pub fn citation_key(apa: &ApaFormat) -> String {
+    const STOP_WORDS: &[&str] = &["a", "an", "the", "of", "on", "in", "and", "to", "el", "la", "los", "las", "un", "una", "de", "del"];
+
+    let author = parse_people(apa.get("authors")).first()
+        .map(|person| key_word(person.family.split_whitespace().next().unwrap_or("")))
+        .unwrap_or_default();
+    let year = PublicationDate::parse(apa.get("date"))
+        .map(|date| date.year.to_string())
+        .unwrap_or_else(|| "nd".to_string());
+    let title = if apa.format == ApaFormatType::Dictionary { apa.get("word") } else { apa.get("title") };
+    let word = title.split_whitespace()
+        .map(key_word)
+        .find(|word| !word.is_empty() && !STOP_WORDS.contains(&word.as_str()))
+        .unwrap_or_default();
+
+    let key = format!("{}{}{}", author, year, word);
+    if key.starts_with(|c: char| c.is_ascii_digit()) { format!("ref{}", key) } else { key }
+}
+
+// Keys for a list of references, repeated ones get a, b, c... like APA does with the years.
+pub fn unique_keys(references: &[ApaFormat]) -> Vec<String> {
+    let keys: Vec<String> = references.iter().map(citation_key).collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for key in &keys {
+        *counts.entry(key.as_str()).or_default() += 1;
+    }
+
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    keys.iter()
+        .map(|key| {
+            if counts[key.as_str()] == 1 {
+                return key.clone();
+            }
+            let index = seen.entry(key.as_str()).or_default();
+            let suffix = key_suffix(*index);
+            *index += 1;
+            format!("{}{}", key, suffix)
+        })
+        .collect()
+}
+
+// a, b... z, then aa, ab... like spreadsheet columns.
+fn key_suffix(mut index: usize) -> String {
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push((b'a' + (index % 26) as u8) as char);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    suffix.iter().rev().collect()
+}
+
+// Lowercase ASCII letters and numbers only, accents are removed.
+fn key_word(word: &str) -> String {
+    word.chars()
+        .map(ascii_fold)
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub fn ascii_fold(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+        'Á' | 'À' | 'Â' | 'Ä' | 'Ã' | 'Å' => 'A',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'É' | 'È' | 'Ê' | 'Ë' => 'E',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' => 'o',
+        'Ó' | 'Ò' | 'Ô' | 'Ö' | 'Õ' | 'Ø' => 'O',
+        'ú' | 'ù' | 'û' | 'ü' => 'u',
+        'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ç' => 'c',
+        'Ç' => 'C',
+        _ => c,
+    }
+}
+
+// "Last, F. and Last, F.", organizations are braced so they aren't split into names.
+pub fn names(people: &[Person]) -> String {
+    people.iter()
+        .map(|person| {
+            if person.is_organization() {
+                format!("{{{}}}", escape_latex(&person.family))
+            } else {
+                format!("{}, {}", escape_latex(&person.family), escape_latex(&person.given))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" and ")
+}
+
+pub fn escape_latex(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.trim().chars() {
+        match c {
+            '\\' => output.push_str("\\textbackslash{}"),
+            '~' => output.push_str("\\textasciitilde{}"),
+            '^' => output.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                output.push('\\');
+                output.push(c);
+            }
+            _ if c.is_control() => {}
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal() -> ApaFormat {
+        let mut apa = ApaFormat::new(ApaFormatType::Journal, None);
+        apa.set("authors", "Pérez, M., & World Health Organization");
+        apa.set("date", "2020");
+        apa.set("title", "The DNA of Ñandú in Chile");
+        apa.set("journal", "Journal of Birds & Bees");
+        apa.set("volume", "12");
+        apa.set("issue", "3");
+        apa.set("pages", "45-67");
+        apa.set("doi", "https://doi.org/10.1000/XYZ");
+        apa
+    }
+
+    #[test]
+    fn journal_fields() {
+        let fields = fields(&journal());
+        let fields: Vec<(&str, &str)> = fields.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        assert_eq!(fields, [
+            ("author", "Pérez, M. and {World Health Organization}"),
+            ("title", "The {DNA} of {Ñandú} in {Chile}"),
+            ("journaltitle", "Journal of {Birds} \\& {Bees}"),
+            ("volume", "12"),
+            ("number", "3"),
+            ("pages", "45--67"),
+            ("date", "2020"),
+            ("doi", "10.1000/XYZ"),
+        ]);
+    }
+
+    #[test]
+    fn chapters_have_a_book_and_editors() {
+        let mut apa = ApaFormat::new(ApaFormatType::Chapter, None);
+        apa.set("title", "A chapter");
+        apa.set("editors", "A. Editor & B. Editor");
+        apa.set("book", "Handbook of APA");
+        let entry = entry(&apa, "key");
+        assert!(entry.starts_with("@incollection{key,\n"));
+        assert!(entry.contains("  booktitle = {Handbook of {APA}},\n"));
+        assert!(entry.contains("  editor = {Editor, A. and Editor, B.},\n"));
+        // Empty fields are left out.
+        assert!(!entry.contains("publisher"));
+    }
+
+    #[test]
+    fn websites_have_the_retrieval_date() {
+        let mut apa = ApaFormat::new(ApaFormatType::Website, None);
+        apa.set("url", "https://example.org/page?utm_source=feed");
+        apa.set("retrieved", "2021-06-01");
+        let fields = fields(&apa);
+        assert!(fields.contains(&("url", "https://example.org/page".to_string())));
+        assert!(fields.contains(&("urldate", "2021-06-01".to_string())));
+    }
+
+    #[test]
+    fn citation_keys() {
+        assert_eq!(citation_key(&journal()), "perez2020dna");
+
+        let mut apa = ApaFormat::new(ApaFormatType::Dictionary, None);
+        apa.set("authors", "American Psychological Association");
+        apa.set("word", "El sesgo");
+        assert_eq!(citation_key(&apa), "americanndsesgo");
+
+        let mut apa = ApaFormat::new(ApaFormatType::Book, None);
+        apa.set("date", "1984");
+        apa.set("title", "A novel");
+        assert_eq!(citation_key(&apa), "ref1984novel");
+    }
+
+    #[test]
+    fn repeated_keys_get_letters() {
+        let references: Vec<ApaFormat> = (0..3).map(|_| journal()).collect();
+        assert_eq!(unique_keys(&references), ["perez2020dnaa", "perez2020dnab", "perez2020dnac"]);
+
+        let mut references: Vec<ApaFormat> = (0..28).map(|_| journal()).collect();
+        references.push(ApaFormat::new(ApaFormatType::Book, None));
+        let keys = unique_keys(&references);
+        assert_eq!(keys[25], "perez2020dnaz");
+        assert_eq!(keys[26], "perez2020dnaaa");
+        assert_eq!(keys[27], "perez2020dnaab");
+        assert_eq!(keys[28], "nd");
+        let mut unique = keys.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), keys.len());
+
+        assert_eq!(key_suffix(51), "az");
+        assert_eq!(key_suffix(52), "ba");
+        assert_eq!(key_suffix(26 + 26 * 26), "aaa");
+    }
+
+    #[test]
+    fn latex_special_characters() {
+        assert_eq!(escape_latex(" 50% of R&D_costs #1 "), "50\\% of R\\&D\\_costs \\#1");
+        assert_eq!(escape_latex("{a} \\ b ~ c^2 $"), "\\{a\\} \\textbackslash{} b \\textasciitilde{} c\\textasciicircum{}2 \\$");
+        assert_eq!(escape_latex("tab\there"), "tabhere");
+    }
+}
@@ -0,0 +1,822 @@
+// Classic BibTeX for the templates that only take apacite or natbib.
+
+use std::collections::HashMap;
+
+use crate::arxiv::ArxivId;
+use crate::biblatex::{self, write_entry_with_macros};
+use crate::date::PublicationDate;
+use crate::import::Candidate;
+use crate::person::{format_editors, format_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BibtexOptions {
+    /// Write á as {\'a} for BibTeX setups that choke on UTF-8.
+    pub tex_accents: bool,
+}
+
+// Classic BibTeX has no @online or @inreference.
+pub fn entry_type(format: ApaFormatType) -> &'static str {
+    match format {
+        ApaFormatType::Website => "misc",
+        ApaFormatType::Newspaper => "article",
+        ApaFormatType::Dictionary => "incollection",
+        ApaFormatType::Journal => "article",
+        ApaFormatType::Chapter => "incollection",
+        ApaFormatType::Book => "book",
+        // The standard styles print the note of @unpublished, it has the archive.
+        ApaFormatType::Preprint => "unpublished",
+        ApaFormatType::None => "misc",
+    }
+}
+
+// The BibLaTeX fields, renamed to the ones BibTeX styles know.
+pub fn fields(apa: &ApaFormat, options: BibtexOptions) -> Vec<(&'static str, String)> {
+    let misc = matches!(entry_type(apa.format), "misc" | "unpublished");
+    let mut fields = Vec::new();
+
+    for (name, value) in biblatex::fields(apa) {
+        match name {
+            "entrysubtype" | "pubstate" => {}
+            "journaltitle" => fields.push(("journal", value)),
+            "organization" if misc => fields.push(("note", value)),
+            "eid" => fields.push(("pages", format!("Article {}", value))),
+            "date" => {
+                if let Some(date) = PublicationDate::parse(&value) {
+                    fields.push(("year", date.year.to_string()));
+                    if let Some(month) = date.month {
+                        fields.push(("month", MONTHS[month as usize - 1].to_string()));
+                    }
+                }
+            }
+            // @misc has no url field in the standard styles.
+            "url" if misc => fields.push(("howpublished", format!("\\url{{{}}}", value))),
+            // apacite prints it as the retrieval date.
+            "urldate" => fields.push(("lastchecked", value)),
+            _ => fields.push((name, value)),
+        }
+    }
+
+    if options.tex_accents {
+        for (name, value) in fields.iter_mut() {
+            if !matches!(*name, "url" | "doi" | "howpublished") {
+                *value = tex_accents(value);
+            }
+        }
+    }
+    fields
+}
+
+// The month macros every style defines, written without braces.
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+pub fn entry(apa: &ApaFormat, key: &str, options: BibtexOptions) -> String {
+    // The month is a macro, so the style writes its name.
+    write_entry_with_macros(entry_type(apa.format), key, &fields(apa, options), &["month"])
+}
+
+pub fn bibliography(references: &[ApaFormat], options: BibtexOptions) -> String {
+    biblatex::unique_keys(references).iter()
+        .zip(references)
+        .map(|(key, apa)| entry(apa, key, options))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// BibTeX styles lowercase titles, words with capitals (names, acronyms) are
+// braced so they are kept. The first word only needs it for inner capitals.
+pub fn protect_capitals(title: &str) -> String {
+    title.split(' ')
+        .enumerate()
+        .map(|(i, word)| {
+            let inner_capital = word.chars().skip(1).any(char::is_uppercase);
+            let capital = word.chars().any(char::is_uppercase);
+            if (i > 0 && capital) || inner_capital {
+                format!("{{{}}}", word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Non-ASCII letters as TeX accent commands, unknown ones are left as they are.
+pub fn tex_accents(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            output.push(c);
+            continue;
+        }
+        let command = match c {
+            'ß' => "\\ss".to_string(),
+            'ø' => "\\o".to_string(),
+            'Ø' => "\\O".to_string(),
+            'å' => "\\aa".to_string(),
+            'Å' => "\\AA".to_string(),
+            'æ' => "\\ae".to_string(),
+            'Æ' => "\\AE".to_string(),
+            'œ' => "\\oe".to_string(),
+            'Œ' => "\\OE".to_string(),
+            'ı' => "\\i".to_string(),
+            'ç' => "\\c{c}".to_string(),
+            'Ç' => "\\c{C}".to_string(),
+            _ => match accent(c) {
+                Some((accent, base)) => format!("\\{}{}", accent, base),
+                None => {
+                    output.push(c);
+                    continue;
+                }
+            },
+        };
+        output.push_str(&format!("{{{}}}", command));
+    }
+    output
+}
+
+// Accent commands, the accented letters and the letters under them.
+const ACCENTS: &[(char, &str, &str)] = &[
+    ('\'', "áéíóúýÁÉÍÓÚÝ", "aeiouyAEIOUY"),
+    ('`', "àèìòùÀÈÌÒÙ", "aeiouAEIOU"),
+    ('^', "âêîôûÂÊÎÔÛ", "aeiouAEIOU"),
+    ('"', "äëïöüÿÄËÏÖÜ", "aeiouyAEIOU"),
+    ('~', "ãñõÃÑÕ", "anoANO"),
+];
+
+// The accent command and the letter under it.
+fn accent(c: char) -> Option<(char, char)> {
+    for (accent, accented, base) in ACCENTS {
+        if let Some(i) = accented.chars().position(|letter| letter == c) {
+            return base.chars().nth(i).map(|base| (*accent, base));
+        }
+    }
+    None
+}
+
+/* Reading */
+
+// An entry of a .bib file, with the @string macros already expanded.
+// Values are kept as they were written, TeX and braces included.
+#[derive(Clone, Debug)]
+pub struct BibEntry {
+    /// Lowercase, "article", "online"...
+    pub entry_type: String,
+    pub key: String,
+    /// Lowercase names, in the order they were written.
+    pub fields: Vec<(String, String)>,
+}
+
+impl BibEntry {
+    // The raw value of a field, empty if it isn't there.
+    pub fn raw(&self, name: &str) -> &str {
+        self.fields.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("")
+    }
+
+    // The value of a field as plain text.
+    pub fn get(&self, name: &str) -> String {
+        decode_tex(self.raw(name))
+    }
+
+    // The first of the fields that has a value, BibLaTeX and BibTeX name some of them differently.
+    fn first(&self, names: &[&str]) -> String {
+        names.iter().map(|name| self.get(name)).find(|value| !value.is_empty()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub struct BibError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for BibError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<BibEntry>, BibError> {
+    let mut parser = BibParser {
+        chars: text.chars().collect(),
+        pos: 0,
+        strings: HashMap::new(),
+    };
+    // Every style defines the month macros.
+    for (i, month) in MONTHS.iter().enumerate() {
+        parser.strings.insert(month.to_string(), (i + 1).to_string());
+    }
+
+    let mut entries = Vec::new();
+    // Anything outside of an entry is a comment.
+    while parser.skip_to('@') {
+        parser.pos += 1;
+        let entry_type = parser.identifier().to_lowercase();
+        parser.skip_whitespace();
+        let close = match parser.next() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(parser.error(&format!("@{} should be followed by {{ or (", entry_type))),
+        };
+
+        match entry_type.as_str() {
+            "comment" | "preamble" => {
+                parser.pos -= 1;
+                parser.skip_group()?;
+            }
+            "string" => {
+                parser.skip_whitespace();
+                let name = parser.identifier().to_lowercase();
+                parser.expect('=')?;
+                let value = parser.value()?;
+                parser.strings.insert(name, value);
+                parser.expect(close)?;
+            }
+            _ => {
+                let key = parser.take_while(|c| c != ',' && c != close).trim().to_string();
+                let mut fields = Vec::new();
+                loop {
+                    parser.skip_whitespace();
+                    match parser.next() {
+                        Some(',') => {}
+                        Some(c) if c == close => break,
+                        _ => return Err(parser.error(&format!("unfinished entry {}", key))),
+                    }
+                    parser.skip_whitespace();
+                    // A comma before the end of the entry is allowed.
+                    if parser.peek() == Some(close) {
+                        parser.pos += 1;
+                        break;
+                    }
+                    let name = parser.identifier().to_lowercase();
+                    if name.is_empty() {
+                        return Err(parser.error(&format!("expected a field name in {}", key)));
+                    }
+                    parser.expect('=')?;
+                    let value = parser.value()?;
+                    fields.push((name, value));
+                }
+                entries.push(BibEntry { entry_type, key, fields });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+struct BibParser {
+    chars: Vec<char>,
+    pos: usize,
+    strings: HashMap<String, String>,
+}
+
+impl BibParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+    fn error(&self, message: &str) -> BibError {
+        let line = self.chars.iter().take(self.pos).filter(|c| **c == '\n').count() + 1;
+        BibError { line, message: message.to_string() }
+    }
+    fn skip_to(&mut self, target: char) -> bool {
+        while let Some(c) = self.peek() {
+            if c == target {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&predicate) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+    fn identifier(&mut self) -> String {
+        self.take_while(|c| c.is_alphanumeric() || "_-:.+/'".contains(c))
+    }
+    fn expect(&mut self, expected: char) -> Result<(), BibError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected {}", expected))),
+        }
+    }
+
+    // {...} or (...) with everything nested inside, returns what's inside.
+    fn skip_group(&mut self) -> Result<String, BibError> {
+        let close = match self.next() {
+            Some('(') => ')',
+            _ => '}',
+        };
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some('{') => depth += 1,
+                Some('}') if depth > 0 => depth -= 1,
+                Some(c) if c == close && depth == 0 => break,
+                Some(_) => {}
+                None => return Err(self.error("missing closing brace")),
+            }
+        }
+        Ok(self.chars[start..self.pos - 1].iter().collect())
+    }
+
+    // {braced}, "quoted", a number or a macro, joined with #.
+    fn value(&mut self) -> Result<String, BibError> {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => value.push_str(&self.skip_group()?),
+                Some('"') => {
+                    self.pos += 1;
+                    let start = self.pos;
+                    let mut depth = 0;
+                    loop {
+                        match self.next() {
+                            Some('{') => depth += 1,
+                            Some('}') if depth > 0 => depth -= 1,
+                            Some('"') if depth == 0 => break,
+                            Some(_) => {}
+                            None => return Err(self.error("missing closing quote")),
+                        }
+                    }
+                    value.extend(&self.chars[start..self.pos - 1]);
+                }
+                Some(c) if c.is_ascii_digit() => value.push_str(&self.take_while(|c| c.is_ascii_digit())),
+                Some(c) if c.is_alphabetic() => {
+                    let name = self.identifier().to_lowercase();
+                    // BibTeX leaves unknown macros empty, the name is more useful.
+                    match self.strings.get(&name) {
+                        Some(expansion) => value.push_str(expansion),
+                        None => value.push_str(&name),
+                    }
+                }
+                _ => return Err(self.error("expected a value")),
+            }
+            self.skip_whitespace();
+            if self.peek() != Some('#') {
+                return Ok(value);
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+// TeX to plain text: accents to letters, -- to dashes, \emph{x} to x and braces removed.
+pub fn decode_tex(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                let command: String = if chars.get(i).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    chars[i..].iter().take_while(|c| c.is_ascii_alphabetic()).collect()
+                } else {
+                    chars.get(i).map(char::to_string).unwrap_or_default()
+                };
+                i += command.chars().count();
+                // Like TeX, the spaces after a word command only end it.
+                if command.chars().all(|c| c.is_ascii_alphabetic()) {
+                    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+                        i += 1;
+                    }
+                }
+
+                // The letter or group the command applies to.
+                let argument = |i: &mut usize| -> String {
+                    match chars.get(*i) {
+                        Some('{') => {
+                            let start = *i + 1;
+                            let mut depth = 0;
+                            while *i < chars.len() {
+                                match chars[*i] {
+                                    '{' => depth += 1,
+                                    '}' => {
+                                        depth -= 1;
+                                        if depth == 0 {
+                                            break;
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                                *i += 1;
+                            }
+                            let end = (*i).min(chars.len());
+                            *i += 1;
+                            decode_tex(&chars[start..end].iter().collect::<String>())
+                        }
+                        Some(c) => {
+                            *i += 1;
+                            c.to_string()
+                        }
+                        None => String::new(),
+                    }
+                };
+
+                match command.as_str() {
+                    "'" | "`" | "^" | "\"" | "~" | "=" | "." | "c" | "v" | "u" | "H" | "k" | "r" => {
+                        let letter = argument(&mut i);
+                        let base = letter.chars().next().map(|c| if c == 'ı' { 'i' } else { c });
+                        if let Some(base) = base {
+                            output.push(accented(command.chars().next().unwrap(), base));
+                            output.extend(letter.chars().skip(1));
+                        }
+                    }
+                    "&" | "%" | "$" | "#" | "_" | "{" | "}" => output.push_str(&command),
+                    "\\" | " " => output.push(' '),
+                    "ss" => output.push('ß'),
+                    "o" => output.push('ø'),
+                    "O" => output.push('Ø'),
+                    "aa" => output.push('å'),
+                    "AA" => output.push('Å'),
+                    "ae" => output.push('æ'),
+                    "AE" => output.push('Æ'),
+                    "oe" => output.push('œ'),
+                    "OE" => output.push('Œ'),
+                    "l" => output.push('ł'),
+                    "L" => output.push('Ł'),
+                    "i" => output.push('ı'),
+                    "j" => output.push('j'),
+                    "textendash" => output.push('–'),
+                    "textemdash" => output.push('—'),
+                    "ldots" | "dots" | "textellipsis" => output.push('…'),
+                    "textquoteright" => output.push('’'),
+                    "textquoteleft" => output.push('‘'),
+                    // \emph{x}, \url{x}, \textit{x}... are their text.
+                    _ if chars.get(i) == Some(&'{') => output.push_str(&argument(&mut i)),
+                    _ => {}
+                }
+            }
+            '{' | '}' | '$' => i += 1,
+            '~' => {
+                output.push(' ');
+                i += 1;
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                if chars.get(i + 2) == Some(&'-') {
+                    output.push('—');
+                    i += 3;
+                } else {
+                    output.push('–');
+                    i += 2;
+                }
+            }
+            '`' if chars.get(i + 1) == Some(&'`') => {
+                output.push('“');
+                i += 2;
+            }
+            '\'' if chars.get(i + 1) == Some(&'\'') => {
+                output.push('”');
+                i += 2;
+            }
+            c if c.is_whitespace() => {
+                if !output.ends_with(' ') && !output.is_empty() {
+                    output.push(' ');
+                }
+                i += 1;
+            }
+            c => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output.trim().to_string()
+}
+
+// The letter with the accent, or the letter alone for the accents that have no table.
+fn accented(accent: char, base: char) -> char {
+    const OTHERS: &[(char, &str, &str)] = &[
+        ('c', "çÇşŞ", "cCsS"),
+        ('v', "čČšŠžŽřŘěĚňŇ", "cCsSzZrReEnN"),
+        ('r', "åÅůŮ", "aAuU"),
+        ('H', "őŐűŰ", "oOuU"),
+        ('k', "ąĄęĘ", "aAeE"),
+        ('=', "āĀēĒīĪōŌūŪ", "aAeEiIoOuU"),
+    ];
+    ACCENTS.iter().chain(OTHERS)
+        .filter(|(command, _, _)| *command == accent)
+        .find_map(|(_, accented, letters)| {
+            letters.chars().position(|letter| letter == base).and_then(|i| accented.chars().nth(i))
+        })
+        .unwrap_or(base)
+}
+
+// Splits a raw value on a word outside of braces, " and " for names.
+fn split_top_level(text: &str, separator: &str) -> Vec<String> {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ if depth == 0 && i >= start
+                && text.get(i..i + separator.len()).is_some_and(|word| word.eq_ignore_ascii_case(separator)) => {
+                parts.push(text[start..i].trim().to_string());
+                start = i + separator.len();
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim().to_string());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+// "Last, First", "First von Last" or {Braced Organization}, separated by "and".
+pub fn parse_names(raw: &str) -> Vec<Person> {
+    let mut people = Vec::new();
+    for name in split_top_level(raw, " and ") {
+        if name == "others" {
+            continue;
+        }
+        // A name in its own braces is never split.
+        if name.starts_with('{') && name.ends_with('}') && split_top_level(&name, " ").len() == 1 && !name[1..].contains('{') {
+            people.push(Person::new(decode_tex(&name), ""));
+            continue;
+        }
+
+        // "von Last, Jr, First" keeps the part before the first comma and the given names.
+        let parts = split_top_level(&name, ",");
+        let (family, given) = match parts.as_slice() {
+            [family, given] | [family, _, given] => (decode_tex(family), decode_tex(given)),
+            _ => {
+                let words = split_top_level(&name, " ");
+                // The last name starts at "von", "de", "van der"... or is the last word.
+                let start = (1..words.len())
+                    .find(|i| words[*i].starts_with(|c: char| c.is_lowercase()))
+                    .unwrap_or(words.len().saturating_sub(1));
+                (decode_tex(&words[start..].join(" ")), decode_tex(&words[..start].join(" ")))
+            }
+        };
+        people.push(Person::new(family, given));
+    }
+    people
+}
+
+// Which format an entry is, and a warning when it's only the closest one.
+pub fn format_of(entry: &BibEntry) -> (ApaFormatType, Option<String>) {
+    let format = match entry.entry_type.as_str() {
+        "article" if matches!(entry.get("entrysubtype").as_str(), "newspaper" | "magazine") => ApaFormatType::Newspaper,
+        "article" => ApaFormatType::Journal,
+        "unpublished" => ApaFormatType::Preprint,
+        // arXiv exports @misc with the eprint, BibLaTeX has the pubstate.
+        "online" | "misc" if entry.get("pubstate") == "preprint" || !entry.first(&["eprinttype", "archiveprefix"]).is_empty() => ApaFormatType::Preprint,
+        "online" | "www" | "electronic" | "webpage" => ApaFormatType::Website,
+        "inreference" | "reference" | "mvreference" => ApaFormatType::Dictionary,
+        "incollection" | "inbook" | "inproceedings" | "conference" => ApaFormatType::Chapter,
+        "book" | "mvbook" | "collection" | "mvcollection" => ApaFormatType::Book,
+        _ => {
+            let format = if !entry.first(&["journaltitle", "journal"]).is_empty() {
+                ApaFormatType::Journal
+            } else if !entry.get("booktitle").is_empty() {
+                ApaFormatType::Chapter
+            } else {
+                ApaFormatType::Website
+            };
+            return (format, Some(format!("@{} {} opened as a {}, check the fields", entry.entry_type, entry.key, format)));
+        }
+    };
+    (format, None)
+}
+
+// The entry in the editor's fields, with the warnings about what didn't fit.
+pub fn to_apa(entry: &BibEntry, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let (format, warning) = format_of(entry);
+    let mut apa = ApaFormat::new(format, Some(lang));
+    let warnings: Vec<String> = warning.into_iter().collect();
+
+    apa.set("authors", format_people(&parse_names(entry.raw("author"))));
+    apa.set("editors", format_editors(&parse_names(entry.raw("editor"))));
+
+    // BibLaTeX has date, BibTeX has year and month.
+    let date = match entry.get("date") {
+        date if !date.is_empty() => date.split('/').next().unwrap_or("").to_string(),
+        _ => format!("{} {}", entry.get("year"), entry.get("month")),
+    };
+    match PublicationDate::parse(&date) {
+        Some(date) => {
+            apa.set_date(&date);
+        }
+        None if !date.trim().is_empty() => {
+            apa.set("date", date.trim());
+        }
+        None => {}
+    }
+
+    let title = entry.get("title");
+    let container = match format {
+        ApaFormatType::Journal | ApaFormatType::Newspaper => entry.first(&["journaltitle", "journal"]),
+        ApaFormatType::Dictionary | ApaFormatType::Chapter => entry.get("booktitle"),
+        ApaFormatType::Preprint => match entry.first(&["organization", "note", "eprinttype", "archiveprefix", "publisher", "institution"]) {
+            archive if archive.eq_ignore_ascii_case("arxiv") => "arXiv".to_string(),
+            archive => archive,
+        },
+        _ => entry.first(&["organization", "publisher", "institution"]),
+    };
+    if let Some(id) = format.container() {
+        apa.set(id, &container);
+    }
+    if !apa.set("title", &title) {
+        apa.set("word", &title);
+    }
+
+    apa.set("volume", entry.get("volume"));
+    apa.set("issue", entry.first(&["number", "issue"]));
+    apa.set("publisher", entry.get("publisher"));
+    apa.set("edition", entry.get("edition"));
+    match (entry.get("pages"), entry.get("eid")) {
+        (pages, _) if !pages.is_empty() => apa.set("pages", &pages),
+        (_, eid) if !eid.is_empty() => apa.set("pages", format!("Article {}", eid)),
+        _ => false,
+    };
+
+    // Links aren't TeX, ~ and % are part of them.
+    apa.set("doi", strip_braces(entry.raw("doi")));
+    let url = match strip_braces(entry.raw("url")) {
+        url if url.is_empty() => entry.raw("howpublished").strip_prefix("\\url").map(strip_braces).unwrap_or_default(),
+        url => url,
+    };
+    // arXiv entries only have the id.
+    let url = match ArxivId::parse(&entry.get("eprint")) {
+        Ok(id) if url.is_empty() && format == ApaFormatType::Preprint && container == "arXiv" => id.url(),
+        _ => url,
+    };
+    apa.set("url", &url);
+    if let Some(date) = PublicationDate::parse(&entry.get("urldate")) {
+        apa.set("retrieved", date.apa(&lang));
+    }
+
+    (apa, warnings)
+}
+
+fn strip_braces(text: &str) -> String {
+    text.chars().filter(|c| !matches!(c, '{' | '}')).collect::<String>().trim().to_string()
+}
+
+// Every entry of a .bib file, ready for the picker.
+pub fn candidates(text: &str, lang: Lang) -> Result<Vec<Candidate>, String> {
+    let entries = parse(text).map_err(|error| error.to_string())?;
+    Ok(entries.iter()
+        .map(|entry| {
+            let (apa, warnings) = to_apa(entry, lang);
+            Candidate::new(&entry.key, apa, warnings)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(text: &str) -> BibEntry {
+        let mut entries = parse(text).unwrap();
+        assert_eq!(entries.len(), 1);
+        entries.remove(0)
+    }
+
+    #[test]
+    fn braces_and_quotes_nest() {
+        let entry = only(r#"@article{key,
+            title = {The {DNA} of {{Nested}} braces},
+            journal = "Quotes {with "inner" quotes}",
+            note = {A "quote" inside braces},
+        }"#);
+        assert_eq!(entry.raw("title"), "The {DNA} of {{Nested}} braces");
+        assert_eq!(entry.get("title"), "The DNA of Nested braces");
+        assert_eq!(entry.raw("journal"), "Quotes {with \"inner\" quotes}");
+        assert_eq!(entry.get("note"), "A \"quote\" inside braces");
+    }
+
+    #[test]
+    fn parentheses_and_comments_around_entries() {
+        let entries = parse("Text before\n@comment{not {an} entry}\n@book(first, title = {One})\n@misc{second, title = {Two},}").unwrap();
+        let keys: Vec<&str> = entries.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, ["first", "second"]);
+    }
+
+    #[test]
+    fn string_macros_are_expanded() {
+        let entry = only(r#"@string{jpsp = "Journal of Personality"}
+            @STRING(soc = {and Social Psychology})
+            @article{key, journal = jpsp # " " # soc, volume = 12}"#);
+        assert_eq!(entry.get("journal"), "Journal of Personality and Social Psychology");
+        assert_eq!(entry.get("volume"), "12");
+    }
+
+    #[test]
+    fn month_macros() {
+        let entry = only("@article{key, year = 2020, month = MAR}");
+        assert_eq!(entry.get("month"), "3");
+        let (apa, _) = to_apa(&entry, Lang::English);
+        assert_eq!(apa.get("date"), "2020");
+
+        let entry = only("@online{key, year = 2020, month = dec # {~24}}");
+        assert_eq!(entry.get("month"), "12 24");
+        let (apa, _) = to_apa(&entry, Lang::English);
+        assert_eq!(apa.get("date"), "2020, December 24");
+    }
+
+    #[test]
+    fn unknown_macros_keep_their_name() {
+        assert_eq!(only("@misc{key, publisher = acm}").get("publisher"), "acm");
+    }
+
+    #[test]
+    fn errors_have_the_line() {
+        let error = parse("@article{key,\n  title = {Unclosed\n").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!(parse("@article key").is_err());
+    }
+
+    #[test]
+    fn the_month_is_written_as_a_macro() {
+        let mut apa = ApaFormat::new(ApaFormatType::Website, None);
+        apa.set("title", "A page");
+        apa.set("date", "2021, May 4");
+        let entry = entry(&apa, "page", BibtexOptions::default());
+        assert!(entry.contains("  month = may,\n"));
+        assert!(entry.contains("  title = {A page},\n"));
+
+        // And it is read back, BibTeX has no day.
+        let (read, _) = to_apa(&only(&entry), Lang::English);
+        assert_eq!(read.get("date"), "2021, May");
+    }
+
+    #[test]
+    fn month_braces_in_values_are_kept() {
+        let mut apa = ApaFormat::new(ApaFormatType::Journal, None);
+        apa.set("title", "Why month = {jan} matters");
+        apa.set("date", "2019");
+        assert!(entry(&apa, "key", BibtexOptions::default()).contains(r"title = {Why month = \{jan\} matters},"));
+    }
+
+    #[test]
+    fn names() {
+        let people = parse_names("van der Berg, Jan and {World Health Organization} and Ada Lovelace and others");
+        let families: Vec<&str> = people.iter().map(|person| person.family.as_str()).collect();
+        assert_eq!(families, ["van der Berg", "World Health Organization", "Lovelace"]);
+    }
+
+    fn preprint() -> ApaFormat {
+        let mut apa = ApaFormat::new(ApaFormatType::Preprint, None);
+        apa.set("authors", "Smith, J. A., & Pérez, M.");
+        apa.set("date", "2021");
+        apa.set("title", "A preprint");
+        apa.set("archive", "PsyArXiv");
+        apa.set("doi", "10.31234/osf.io/abcde");
+        apa
+    }
+
+    #[test]
+    fn preprints_round_trip() {
+        let apa = preprint();
+        let (read, warnings) = to_apa(&only(&entry(&apa, "key", BibtexOptions::default())), Lang::English);
+        assert!(warnings.is_empty());
+        assert!(read.format == ApaFormatType::Preprint);
+        assert_eq!(read.data, apa.data);
+
+        // BibLaTeX writes @online with the pubstate.
+        let (read, warnings) = to_apa(&only(&biblatex::entry(&apa, "key")), Lang::English);
+        assert!(warnings.is_empty());
+        assert!(read.format == ApaFormatType::Preprint);
+        assert_eq!(read.data, apa.data);
+    }
+
+    #[test]
+    fn arxiv_entries() {
+        let entry = only(r#"@misc{vaswani2017attention,
+            title = {Attention Is All You Need},
+            author = {Ashish Vaswani and Noam Shazeer},
+            year = {2017},
+            eprint = {1706.03762},
+            archivePrefix = {arXiv},
+            primaryClass = {cs.CL},
+        }"#);
+        let (apa, warnings) = to_apa(&entry, Lang::English);
+        assert!(warnings.is_empty());
+        assert!(apa.format == ApaFormatType::Preprint);
+        assert_eq!(apa.get("archive"), "arXiv");
+        assert_eq!(apa.get("url"), "https://arxiv.org/abs/1706.03762");
+    }
+}
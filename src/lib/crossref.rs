@@ -0,0 +1,243 @@
+// Crossref work metadata, so a DOI is enough to fill a reference.
+
+use serde_json::Value;
+
+use crate::csl::read_date;
+use crate::doi::{percent_encode, Doi};
+use crate::fetch::{join, FetchError, HttpClient};
+use crate::person::{format_book_editors, format_editors, format_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// The reference of a DOI, with warnings about what didn't fit.
+pub fn lookup(client: &dyn HttpClient, base_url: &str, doi: &Doi, lang: Lang) -> Result<(ApaFormat, Vec<String>), FetchError> {
+    fetch_work(client, base_url, doi).map(|work| from_work(&work, lang))
+}
+
+// The work as Crossref describes it, for what doesn't go in the fields.
+pub fn fetch_work(client: &dyn HttpClient, base_url: &str, doi: &Doi) -> Result<Value, FetchError> {
+    let url = join(base_url, &format!("works/{}", percent_encode(&doi.to_string())));
+    let body = client.get(&url, "application/json")?;
+    let mut value: Value = serde_json::from_str(&body).map_err(|error| FetchError::Invalid(error.to_string()))?;
+    match value.get_mut("message") {
+        Some(work) if work.is_object() => Ok(work.take()),
+        _ => Err(FetchError::Invalid("no work in the answer".to_string())),
+    }
+}
+
+// Which format a Crossref type is, and a warning when it's only the closest one.
+pub fn format_of(work: &Value) -> (ApaFormatType, Option<String>) {
+    let work_type = text(work, "type");
+    let format = match work_type.as_str() {
+        "journal-article" => ApaFormatType::Journal,
+        "book-chapter" | "book-section" | "book-part" | "proceedings-article" => ApaFormatType::Chapter,
+        "reference-entry" => ApaFormatType::Dictionary,
+        "book" | "edited-book" | "monograph" | "reference-book" | "book-set" => ApaFormatType::Book,
+        "posted-content" => ApaFormatType::Preprint,
+        _ => {
+            let format = if text(work, "container-title").is_empty() { ApaFormatType::Book } else { ApaFormatType::Journal };
+            return (format, Some(format!("Crossref type \"{}\" opened as a {}, check the fields", work_type, format)));
+        }
+    };
+    (format, None)
+}
+
+pub fn from_work(work: &Value, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let (format, warning) = format_of(work);
+    let mut apa = ApaFormat::new(format, Some(lang));
+    let warnings: Vec<String> = warning.into_iter().collect();
+
+    let authors = people(work.get("author"));
+    let editors = people(work.get("editor"));
+    if authors.is_empty() && !editors.is_empty() && format == ApaFormatType::Book {
+        // Edited books go by their editors.
+        apa.set("authors", format_book_editors(&editors));
+    } else {
+        apa.set("authors", format_people(&authors));
+    }
+    apa.set("editors", format_editors(&editors));
+
+    // The print date is the one on the cover, issued is the earliest of them.
+    if let Some(date) = ["published-print", "issued", "published-online"].iter().find_map(|key| read_date(work.get(*key))) {
+        apa.set_date(&date);
+    }
+
+    let title = text(work, "title");
+    let subtitle = text(work, "subtitle");
+    let title = if subtitle.is_empty() || title.contains(':') { title } else { format!("{}: {}", title, subtitle) };
+    if !apa.set("title", title.as_str()) {
+        apa.set("word", title);
+    }
+    if format == ApaFormatType::Preprint {
+        apa.set("archive", archive(work));
+    } else if let Some(id) = format.container() {
+        apa.set(id, text(work, "container-title"));
+    }
+
+    apa.set("volume", text(work, "volume"));
+    apa.set("issue", text(work, "issue"));
+    apa.set("publisher", text(work, "publisher"));
+    apa.set("edition", text(work, "edition-number"));
+    match (text(work, "page"), text(work, "article-number")) {
+        (page, _) if !page.is_empty() => apa.set("pages", page),
+        (_, number) if !number.is_empty() => apa.set("pages", format!("Article {}", number)),
+        _ => false,
+    };
+    apa.set("doi", text(work, "DOI"));
+
+    (apa, warnings)
+}
+
+// The DOI of the article a preprint became, if the archive told Crossref.
+pub fn published_version(work: &Value) -> Option<Doi> {
+    work.get("relation")?
+        .get("is-preprint-of")?
+        .as_array()?
+        .iter()
+        .filter(|relation| relation.get("id-type").and_then(Value::as_str) == Some("doi"))
+        .find_map(|relation| Doi::parse(relation.get("id")?.as_str()?).ok())
+}
+
+// Preprint servers name themselves as the institution, the publisher is who runs them.
+fn archive(work: &Value) -> String {
+    let institution = work.get("institution")
+        .and_then(Value::as_array)
+        .and_then(|institutions| institutions.first())
+        .map(|institution| text(institution, "name"))
+        .unwrap_or_default();
+    if !institution.is_empty() {
+        return institution;
+    }
+    let group = text(work, "group-title");
+    if !group.is_empty() {
+        return group;
+    }
+    let prefix = text(work, "DOI").split('/').next().unwrap_or("").to_string();
+    let known = match prefix.as_str() {
+        "10.1101" => "bioRxiv",
+        "10.31234" => "PsyArXiv",
+        "10.31235" => "SocArXiv",
+        "10.31219" => "OSF Preprints",
+        "10.21203" => "Research Square",
+        "10.20944" => "Preprints",
+        "10.26434" => "ChemRxiv",
+        "10.2139" => "SSRN",
+        "10.48550" => "arXiv",
+        _ => "",
+    };
+    if known.is_empty() { text(work, "publisher") } else { known.to_string() }
+}
+
+// Titles come as lists and with markup like <i> in them.
+fn text(work: &Value, key: &str) -> String {
+    let text = match work.get(key) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Number(number)) => number.to_string(),
+        Some(Value::Array(values)) => values.first().and_then(Value::as_str).unwrap_or("").to_string(),
+        _ => String::new(),
+    };
+    strip_tags(&text).split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+pub fn strip_tags(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+    output
+}
+
+// {"given", "family"}, organizations only have a name.
+fn people(value: Option<&Value>) -> Vec<Person> {
+    value.and_then(Value::as_array)
+        .map(|people| {
+            people.iter()
+                .filter_map(|person| {
+                    let family = text(person, "family");
+                    if family.is_empty() {
+                        let name = text(person, "name");
+                        (!name.is_empty()).then(|| Person::new(name, ""))
+                    } else {
+                        Some(Person::new(family, text(person, "given")))
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FakeClient;
+    use crate::person::parse_people;
+
+    const BASE: &str = "https://api.crossref.org";
+
+    fn client() -> FakeClient {
+        FakeClient::new(&[
+            ("https://api.crossref.org/works/10.1037/0033-295X.84.2.191", include_str!("../../tests/fixtures/crossref/journal-article.json")),
+            ("https://api.crossref.org/works/10.1017/CBO9780511816796", include_str!("../../tests/fixtures/crossref/edited-book.json")),
+            ("https://api.crossref.org/works/10.1101/2020.03.22.002386", include_str!("../../tests/fixtures/crossref/posted-content.json")),
+        ])
+    }
+
+    fn lookup_doi(doi: &str) -> Result<(ApaFormat, Vec<String>), FetchError> {
+        lookup(&client(), BASE, &Doi::parse(doi).unwrap(), Lang::English)
+    }
+
+    #[test]
+    fn journal_article() {
+        let (apa, warnings) = lookup_doi("10.1037/0033-295X.84.2.191").unwrap();
+        assert!(warnings.is_empty());
+        assert!(apa.format == ApaFormatType::Journal);
+        assert_eq!(apa.get("authors"), "Bandura, A.");
+        assert_eq!(apa.get("date"), "1977");
+        // The markup is left out.
+        assert_eq!(apa.get("title"), "Self-efficacy: Toward a unifying theory of behavioral change.");
+        assert_eq!(apa.get("journal"), "Psychological Review");
+        assert_eq!((apa.get("volume"), apa.get("issue"), apa.get("pages")), ("84", "4", "191-215"));
+        assert_eq!(apa.get("doi"), "10.1037/0033-295X.84.2.191");
+    }
+
+    #[test]
+    fn edited_books_go_by_their_editors() {
+        let (apa, warnings) = lookup_doi("https://doi.org/10.1017/CBO9780511816796").unwrap();
+        assert!(warnings.is_empty());
+        assert!(apa.format == ApaFormatType::Book);
+        assert_eq!(apa.get("authors"), "Ericsson, K. A., Charness, N., Feltovich, P. J., & Hoffman, R. R. (Eds.)");
+        assert_eq!(apa.get("date"), "2006");
+        assert_eq!(apa.get("publisher"), "Cambridge University Press");
+        // The exports still see four people.
+        assert_eq!(parse_people(apa.get("authors")).len(), 4);
+    }
+
+    #[test]
+    fn preprints_and_the_article_they_became() {
+        let doi = Doi::parse("10.1101/2020.03.22.002386").unwrap();
+        let work = fetch_work(&client(), BASE, &doi).unwrap();
+        let (apa, _) = from_work(&work, Lang::English);
+        assert!(apa.format == ApaFormatType::Preprint);
+        assert_eq!(apa.get("authors"), "Gordon, D. E., Jang, G. M., & QCRG Structural Biology Consortium");
+        assert_eq!(apa.get("date"), "2020");
+        assert_eq!(apa.get("archive"), "bioRxiv");
+        assert_eq!(published_version(&work), Doi::parse("10.1038/s41586-020-2286-9").ok());
+    }
+
+    #[test]
+    fn unknown_dois_are_not_found() {
+        assert_eq!(lookup_doi("10.1000/does-not-exist").err(), Some(FetchError::NotFound));
+    }
+
+    #[test]
+    fn answers_without_a_work_are_invalid() {
+        let client = FakeClient::new(&[("https://api.crossref.org/works/10.1000/xyz", r#"{"status":"ok","message":[]}"#)]);
+        let error = lookup(&client, BASE, &Doi::parse("10.1000/xyz").unwrap(), Lang::English).err();
+        assert!(matches!(error, Some(FetchError::Invalid(_))));
+    }
+}
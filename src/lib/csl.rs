@@ -0,0 +1,367 @@
+// CSL-JSON, read by Pandoc, Zotero and every citeproc processor.
+
+use serde_json::{json, Map, Value};
+
+use crate::biblatex::unique_keys;
+use crate::date::PublicationDate;
+use crate::locator::PageLocator;
+use crate::import::Candidate;
+use crate::person::{format_editors, format_people, parse_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// CSL type of each format.
+pub fn csl_type(format: ApaFormatType) -> &'static str {
+    match format {
+        ApaFormatType::Website => "webpage",
+        ApaFormatType::Newspaper => "article-newspaper",
+        ApaFormatType::Dictionary => "entry-dictionary",
+        ApaFormatType::Journal => "article-journal",
+        ApaFormatType::Chapter => "chapter",
+        ApaFormatType::Book => "book",
+        // What Zotero exports preprints as.
+        ApaFormatType::Preprint => "article",
+        ApaFormatType::None => "document",
+    }
+}
+
+pub fn item(apa: &ApaFormat, id: &str) -> Value {
+    let mut item = Map::new();
+    item.insert("id".to_string(), json!(id));
+    item.insert("type".to_string(), json!(csl_type(apa.format)));
+
+    let mut text = |name: &str, value: &str| {
+        if !value.trim().is_empty() {
+            item.insert(name.to_string(), json!(value.trim()));
+        }
+    };
+
+    match apa.format {
+        ApaFormatType::Dictionary => {
+            text("title", apa.get("word"));
+            text("container-title", apa.get("dictionary"));
+        }
+        ApaFormatType::Chapter => {
+            text("title", apa.get("title"));
+            text("container-title", apa.get("book"));
+        }
+        ApaFormatType::Journal => {
+            text("title", apa.get("title"));
+            text("container-title", apa.get("journal"));
+            text("volume", apa.get("volume"));
+            text("issue", apa.get("issue"));
+        }
+        ApaFormatType::Newspaper => {
+            text("title", apa.get("title"));
+            text("container-title", apa.get("newspaper"));
+        }
+        ApaFormatType::Website => {
+            text("title", apa.get("title"));
+            text("container-title", apa.get("website"));
+        }
+        ApaFormatType::Book => {
+            text("title", apa.get("title"));
+            text("edition", apa.get("edition"));
+        }
+        ApaFormatType::Preprint => {
+            text("title", apa.get("title"));
+            text("genre", "Preprint");
+            text("publisher", apa.get("archive"));
+        }
+        ApaFormatType::None => {}
+    }
+    text("publisher", apa.get("publisher"));
+
+    match PageLocator::parse(apa.get("pages")) {
+        Some((PageLocator::Single(page), _)) => text("page", &page),
+        Some((PageLocator::Range(start, end), _)) => text("page", &format!("{}-{}", start, end)),
+        Some((PageLocator::Article(number), _)) => text("number", &number),
+        None => {}
+    }
+
+    if let Some(doi) = apa.doi() {
+        text("DOI", &doi.to_string());
+    }
+    if !apa.get("url").is_empty() {
+        text("URL", &apa.url());
+    }
+
+    for (name, field) in [("author", "authors"), ("editor", "editors")] {
+        let people = parse_people(apa.get(field));
+        if !people.is_empty() {
+            item.insert(name.to_string(), Value::Array(people.iter().map(name_object).collect()));
+        }
+    }
+
+    if let Some(date) = PublicationDate::parse(apa.get("date")) {
+        item.insert("issued".to_string(), date_object(&date));
+    }
+    if !apa.get("url").is_empty() && apa.doi().is_none()
+        && matches!(apa.format, ApaFormatType::Website | ApaFormatType::Newspaper | ApaFormatType::Dictionary) {
+        item.insert("accessed".to_string(), date_object(&apa.retrieved()));
+    }
+
+    Value::Object(item)
+}
+
+// An array of items, even when there is only one reference.
+pub fn items(references: &[ApaFormat]) -> String {
+    let items: Vec<Value> = unique_keys(references).iter()
+        .zip(references)
+        .map(|(id, apa)| item(apa, id))
+        .collect();
+    serde_json::to_string_pretty(&Value::Array(items)).unwrap()
+}
+
+// Organizations have a single literal name.
+fn name_object(person: &Person) -> Value {
+    if person.is_organization() {
+        json!({ "literal": person.family })
+    } else {
+        json!({ "family": person.family, "given": person.given })
+    }
+}
+
+// {"date-parts": [[2020, 5, 3]]}, with only the parts that are known.
+fn date_object(date: &PublicationDate) -> Value {
+    let mut parts = vec![json!(date.year)];
+    if let Some(month) = date.month {
+        parts.push(json!(month));
+        if let Some(day) = date.day {
+            parts.push(json!(day));
+        }
+    }
+    json!({ "date-parts": [parts] })
+}
+
+/* Reading */
+
+// Which format a CSL type is, and a warning when it's only the closest one.
+pub fn format_of(item: &Value) -> (ApaFormatType, Option<String>) {
+    let csl_type = text(item, "type");
+    // Preprints are "article", or any type with the genre.
+    if csl_type == "article" || text(item, "genre").eq_ignore_ascii_case("preprint") {
+        return (ApaFormatType::Preprint, None);
+    }
+    let format = match csl_type.as_str() {
+        "article-journal" => ApaFormatType::Journal,
+        "article-newspaper" | "article-magazine" => ApaFormatType::Newspaper,
+        "webpage" | "post-weblog" | "post" => ApaFormatType::Website,
+        "entry-dictionary" | "entry-encyclopedia" | "entry" => ApaFormatType::Dictionary,
+        "chapter" | "paper-conference" => ApaFormatType::Chapter,
+        "book" => ApaFormatType::Book,
+        _ => {
+            let has = |key: &str| !text(item, key).is_empty() || item.get(key).is_some_and(Value::is_array);
+            let format = if has("container-title") && (has("volume") || has("page")) {
+                ApaFormatType::Journal
+            } else if has("container-title") && (has("editor") || has("publisher")) {
+                ApaFormatType::Chapter
+            } else {
+                ApaFormatType::Website
+            };
+            return (format, Some(format!("CSL type \"{}\" opened as a {}, check the fields", csl_type, format)));
+        }
+    };
+    (format, None)
+}
+
+// The item in the editor's fields, with the warnings about what didn't fit.
+pub fn to_apa(item: &Value, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let (format, warning) = format_of(item);
+    let mut apa = ApaFormat::new(format, Some(lang));
+
+    apa.set("authors", format_people(&names(item.get("author"))));
+    apa.set("editors", format_editors(&names(item.get("editor"))));
+    if let Some(date) = read_date(item.get("issued")) {
+        apa.set_date(&date);
+    }
+
+    let title = text(item, "title");
+    if !apa.set("title", title.as_str()) {
+        apa.set("word", title);
+    }
+    // The archive of a preprint is its publisher.
+    let container = match text(item, "container-title") {
+        container if container.is_empty() && format == ApaFormatType::Preprint => text(item, "publisher"),
+        container => container,
+    };
+    if let Some(id) = format.container() {
+        apa.set(id, container);
+    }
+
+    apa.set("volume", text(item, "volume"));
+    apa.set("issue", text(item, "issue"));
+    apa.set("publisher", text(item, "publisher"));
+    apa.set("edition", text(item, "edition"));
+    match (text(item, "page"), text(item, "number")) {
+        (page, _) if !page.is_empty() => apa.set("pages", page),
+        (_, number) if !number.is_empty() && format == ApaFormatType::Journal => apa.set("pages", format!("Article {}", number)),
+        _ => false,
+    };
+
+    apa.set("doi", text(item, "DOI"));
+    apa.set("url", text(item, "URL"));
+    if let Some(date) = read_date(item.get("accessed")) {
+        apa.set("retrieved", date.apa(&lang));
+    }
+
+    (apa, warning.into_iter().collect())
+}
+
+// The items of a file, an array or a single item.
+pub fn candidates(text: &str, lang: Lang) -> Result<Vec<Candidate>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+    let items = match value {
+        Value::Array(items) => items,
+        item @ Value::Object(_) => vec![item],
+        _ => return Err("expected an array of CSL items".to_string()),
+    };
+    Ok(items.iter()
+        .filter(|item| item.is_object())
+        .map(|item| {
+            let (apa, warnings) = to_apa(item, lang);
+            Candidate::new(&self::text(item, "id"), apa, warnings)
+        })
+        .collect())
+}
+
+// A string or number field as text, empty if it isn't there.
+fn text(item: &Value, key: &str) -> String {
+    match item.get(key) {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Number(number)) => number.to_string(),
+        // Some exporters write the container title as a list.
+        Some(Value::Array(values)) => values.first().and_then(Value::as_str).unwrap_or("").trim().to_string(),
+        _ => String::new(),
+    }
+}
+
+// {"family", "given"} objects, organizations have a literal name.
+fn names(value: Option<&Value>) -> Vec<Person> {
+    let names = match value {
+        Some(Value::Array(names)) => names,
+        _ => return Vec::new(),
+    };
+    names.iter()
+        .filter_map(|name| {
+            let literal = text(name, "literal");
+            if !literal.is_empty() {
+                return Some(Person::new(literal, ""));
+            }
+            // "van" in "van Gogh" goes with the last name.
+            let family = [text(name, "non-dropping-particle"), text(name, "family")]
+                .iter()
+                .filter(|part| !part.is_empty())
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(" ");
+            (!family.is_empty()).then(|| Person::new(family, text(name, "given")))
+        })
+        .collect()
+}
+
+// {"date-parts": [[2020, 5, 3]]}, or the date written out in "raw" or "literal".
+pub fn read_date(value: Option<&Value>) -> Option<PublicationDate> {
+    let value = value?;
+    if let Some(text) = value.as_str() {
+        return PublicationDate::parse(text);
+    }
+    if let Some(parts) = value.get("date-parts").and_then(|parts| parts.get(0)).and_then(Value::as_array) {
+        // Parts can be numbers or strings.
+        let part = |i: usize| -> Option<u32> {
+            parts.get(i).and_then(|part| part.as_u64().map(|n| n as u32).or_else(|| part.as_str()?.trim().parse().ok()))
+        };
+        if let Some(year) = part(0) {
+            let month = part(1).filter(|month| (1..=12).contains(month));
+            let day = part(2).filter(|day| month.is_some() && (1..=31).contains(day));
+            return Some(PublicationDate { year: year as i32, month, day });
+        }
+    }
+    PublicationDate::parse(&text(value, "raw")).or_else(|| PublicationDate::parse(&text(value, "literal")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_parts() {
+        assert_eq!(read_date(Some(&json!({ "date-parts": [[2020, 5, 3]] }))), Some(PublicationDate { year: 2020, month: Some(5), day: Some(3) }));
+        // Strings, and a day without a month is dropped.
+        assert_eq!(read_date(Some(&json!({ "date-parts": [["2019", "13", "4"]] }))), Some(PublicationDate { year: 2019, month: None, day: None }));
+        // Ranges only keep the start.
+        assert_eq!(read_date(Some(&json!({ "date-parts": [[2018, 2], [2018, 3]] }))), Some(PublicationDate { year: 2018, month: Some(2), day: None }));
+        assert_eq!(read_date(Some(&json!({ "date-parts": [[]] }))), None);
+    }
+
+    #[test]
+    fn raw_and_literal_dates() {
+        assert_eq!(read_date(Some(&json!({ "raw": "2017-04-09" }))), Some(PublicationDate { year: 2017, month: Some(4), day: Some(9) }));
+        assert_eq!(read_date(Some(&json!({ "literal": "2016" }))), Some(PublicationDate { year: 2016, month: None, day: None }));
+        assert_eq!(read_date(Some(&json!({ "literal": "n.d." }))), None);
+        assert_eq!(read_date(None), None);
+    }
+
+    #[test]
+    fn literal_names_and_particles() {
+        let item = json!({
+            "type": "article-journal",
+            "author": [
+                { "family": "Gogh", "given": "Vincent", "non-dropping-particle": "van" },
+                { "literal": "World Health Organization" },
+                { "given": "Nobody" },
+            ],
+        });
+        let (apa, _) = to_apa(&item, Lang::English);
+        assert_eq!(apa.get("authors"), "van Gogh, V., & World Health Organization");
+    }
+
+    #[test]
+    fn a_single_item_or_an_array() {
+        assert_eq!(candidates(r#"{"id": "one", "type": "book", "title": "One"}"#, Lang::English).unwrap().len(), 1);
+        assert_eq!(candidates(r#"[{"id": "a", "type": "book"}, 3, {"id": "b", "type": "chapter"}]"#, Lang::English).unwrap().len(), 2);
+        assert!(candidates("\"text\"", Lang::English).is_err());
+    }
+
+    #[test]
+    fn unknown_types_are_guessed() {
+        let (format, warning) = format_of(&json!({ "type": "report", "container-title": ["A Journal"], "volume": 3 }));
+        assert!(format == ApaFormatType::Journal);
+        assert!(warning.is_some());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut apa = ApaFormat::new(ApaFormatType::Chapter, None);
+        apa.set("authors", "Smith, J. A.");
+        apa.set("date", "2020");
+        apa.set("title", "A chapter");
+        apa.set("editors", "A. Editor & B. Editor");
+        apa.set("book", "A Book");
+        // CSL writes ranges with a hyphen.
+        apa.set("pages", "10-20");
+        apa.set("publisher", "A Press");
+
+        let (read, warnings) = to_apa(&item(&apa, "key"), Lang::English);
+        assert!(warnings.is_empty());
+        assert_eq!(read.data, apa.data);
+    }
+
+    #[test]
+    fn preprints_round_trip() {
+        let mut apa = ApaFormat::new(ApaFormatType::Preprint, None);
+        apa.set("authors", "Smith, J. A., & Pérez, M.");
+        apa.set("date", "2021");
+        apa.set("title", "A preprint");
+        apa.set("archive", "PsyArXiv");
+        apa.set("doi", "10.31234/osf.io/abcde");
+
+        let (read, warnings) = to_apa(&item(&apa, "key"), Lang::English);
+        assert!(warnings.is_empty());
+        assert!(read.format == ApaFormatType::Preprint);
+        assert_eq!(read.data, apa.data);
+
+        // Other exporters keep their type and only say the genre.
+        let (format, _) = format_of(&json!({ "type": "report", "genre": "preprint" }));
+        assert!(format == ApaFormatType::Preprint);
+    }
+}
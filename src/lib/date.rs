@@ -4,24 +4,20 @@ use chrono::prelude::*;
 
 use crate::Lang;
 
-pub fn retrive_current_date(lang: &Lang) -> String {
-
-    let local_date = Local::now();
-   
-    let month = parse_month(&lang, local_date.month());
-    let day = local_date.day();
-    let year = local_date.year();
+// The day a page was consulted.
+pub fn retrieved_on(date: &PublicationDate, lang: &Lang) -> String {
+    let month = parse_month(lang, date.month.unwrap_or(1));
+    let year = date.year;
 
     // Build up the string.
-    let format = match lang {
-        Lang::English => {
-            format!("Retrived {} {}, {}, from", month, day, year)
-        }
-        Lang::Spanish => {
-            format!("Consultado el {} de {}, {}, de", day, month, year)
-        }
-    };
-    return format;
+    match (lang, date.month, date.day) {
+        (Lang::English, Some(_), Some(day)) => format!("Retrived {} {}, {}, from", month, day, year),
+        (Lang::Spanish, Some(_), Some(day)) => format!("Consultado el {} de {}, {}, de", day, month, year),
+        (Lang::English, Some(_), None) => format!("Retrived {} {}, from", month, year),
+        (Lang::Spanish, Some(_), None) => format!("Consultado en {} de {}, de", month, year),
+        (Lang::English, None, _) => format!("Retrived {}, from", year),
+        (Lang::Spanish, None, _) => format!("Consultado en {}, de", year),
+    }
 }
 
 pub fn parse_month(lang: &Lang, month_number: u32) -> String {
@@ -63,5 +59,92 @@ pub fn parse_month(lang: &Lang, month_number: u32) -> String {
         }
     };
 
-    return month.to_string();
-}
\ No newline at end of file
+    month.to_string()
+}
+
+// A publication date as the user typed it: "2020", "2020, May 5", "May 5, 2020", "2020-05-05"...
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicationDate {
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+impl PublicationDate {
+    pub fn parse(text: &str) -> Option<PublicationDate> {
+        let mut year = None;
+        let mut month = None;
+        let mut numbers = Vec::new();
+
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+            if let Ok(number) = word.parse::<u32>() {
+                if word.len() == 4 && year.is_none() {
+                    year = Some(number as i32);
+                } else {
+                    numbers.push(number);
+                }
+            } else if month.is_none() {
+                month = month_number(word);
+            }
+        }
+
+        // "2020-05-05" has the month as a number, "May 5" only has the day.
+        let (month, day) = match (month, numbers.as_slice()) {
+            (Some(month), [day, ..]) => (Some(month), Some(*day)),
+            (Some(month), []) => (Some(month), None),
+            (None, [month, day, ..]) => (Some(*month), Some(*day)),
+            (None, [month]) => (Some(*month), None),
+            (None, []) => (None, None),
+        };
+        let month = month.filter(|month| (1..=12).contains(month));
+        let day = day.filter(|day| month.is_some() && (1..=31).contains(day));
+
+        year.map(|year| PublicationDate { year, month, day })
+    }
+
+    // 2020-05-05, 2020-05 or 2020.
+    pub fn iso(&self) -> String {
+        match (self.month, self.day) {
+            (Some(month), Some(day)) => format!("{:04}-{:02}-{:02}", self.year, month, day),
+            (Some(month), None) => format!("{:04}-{:02}", self.year, month),
+            _ => format!("{:04}", self.year),
+        }
+    }
+
+    // The date formatted like APA wants it inside the parentheses.
+    pub fn apa(&self, lang: &Lang) -> String {
+        match (self.month, self.day) {
+            (Some(month), Some(day)) => match lang {
+                Lang::English => format!("{}, {} {}", self.year, parse_month(lang, month), day),
+                Lang::Spanish => format!("{}, {} de {}", self.year, day, parse_month(lang, month)),
+            },
+            (Some(month), None) => format!("{}, {}", self.year, parse_month(lang, month)),
+            _ => self.year.to_string(),
+        }
+    }
+}
+
+// The number of a month written in English or Spanish, abbreviations work too.
+pub fn month_number(word: &str) -> Option<u32> {
+    const ENGLISH: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    const SPANISH: [&str; 12] = ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"];
+
+    let word = word.to_lowercase();
+    if word.len() < 3 {
+        return None;
+    }
+    let start: String = word.chars().take(3).collect();
+    ENGLISH.iter().position(|month| *month == start)
+        .or_else(|| SPANISH.iter().position(|month| *month == start))
+        .map(|i| i as u32 + 1)
+}
+
+// Today's date, for the date a page was consulted.
+pub fn today() -> PublicationDate {
+    let local_date = Local::now();
+    PublicationDate {
+        year: local_date.year(),
+        month: Some(local_date.month()),
+        day: Some(local_date.day()),
+    }
+}
@@ -0,0 +1,262 @@
+// .docx and .odt reference pages, made without Office installed.
+
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::{fs, path::Path};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::output::in_reference_order;
+use crate::rich::{escape_html, strip_control, Backend};
+use crate::url::Url;
+use crate::{ApaFormat, Lang};
+
+fn escape_xml(text: &str) -> String {
+    escape_html(&strip_control(text))
+}
+
+/* Word */
+
+// Runs of a Word paragraph. Links need a relationship each, so they are collected.
+struct DocxRuns {
+    links: RefCell<Vec<String>>,
+}
+impl Backend for DocxRuns {
+    fn plain(&self, text: &str) -> String {
+        format!("<w:r><w:t xml:space=\"preserve\">{}</w:t></w:r>", escape_xml(text))
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("<w:r><w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\">{}</w:t></w:r>", escape_xml(text))
+    }
+    fn link(&self, url: &str) -> String {
+        // A placeholder like "URL" can't be the target of a relationship.
+        if Url::parse(url).is_err() {
+            return self.plain(url);
+        }
+        let mut links = self.links.borrow_mut();
+        links.push(url.to_string());
+        format!(
+            "<w:hyperlink r:id=\"rIdLink{}\"><w:r><w:rPr><w:color w:val=\"0563C1\"/><w:u w:val=\"single\"/></w:rPr><w:t>{}</w:t></w:r></w:hyperlink>",
+            links.len(), escape_xml(url),
+        )
+    }
+}
+
+const DOCX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+<Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+<Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+</Types>"#;
+
+const DOCX_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+</Relationships>"#;
+
+// Times New Roman 12, like APA papers.
+const DOCX_STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Times New Roman" w:hAnsi="Times New Roman" w:cs="Times New Roman"/><w:sz w:val="24"/></w:rPr></w:rPrDefault></w:docDefaults>
+</w:styles>"#;
+
+pub fn docx(references: &[ApaFormat]) -> io::Result<Vec<u8>> {
+    let runs = DocxRuns { links: RefCell::new(Vec::new()) };
+
+    // Double spaced, the references with a 0.5 inch (720 twips) hanging indent.
+    let mut body = format!(
+        "<w:p><w:pPr><w:jc w:val=\"center\"/><w:spacing w:line=\"480\" w:lineRule=\"auto\"/></w:pPr><w:r><w:rPr><w:b/></w:rPr><w:t>{}</w:t></w:r></w:p>\n",
+        Lang::of(references).references_heading(),
+    );
+    for apa in in_reference_order(references) {
+        body.push_str(&format!(
+            "<w:p><w:pPr><w:spacing w:line=\"480\" w:lineRule=\"auto\"/><w:ind w:left=\"720\" w:hanging=\"720\"/></w:pPr>{}</w:p>\n",
+            apa.reference().render(&runs),
+        ));
+    }
+
+    let document = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<w:body>
+{}<w:sectPr><w:pgSz w:w="12240" w:h="15840"/><w:pgMar w:top="1440" w:right="1440" w:bottom="1440" w:left="1440" w:header="720" w:footer="720" w:gutter="0"/></w:sectPr>
+</w:body>
+</w:document>"#,
+        body,
+    );
+
+    let mut relationships = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rIdStyles" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+"#,
+    );
+    for (i, url) in runs.links.borrow().iter().enumerate() {
+        relationships.push_str(&format!(
+            "<Relationship Id=\"rIdLink{}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink\" Target=\"{}\" TargetMode=\"External\"/>\n",
+            i + 1, escape_xml(url),
+        ));
+    }
+    relationships.push_str("</Relationships>");
+
+    zip_files(&[
+        ("[Content_Types].xml", DOCX_CONTENT_TYPES),
+        ("_rels/.rels", DOCX_RELS),
+        ("word/document.xml", &document),
+        ("word/styles.xml", DOCX_STYLES),
+        ("word/_rels/document.xml.rels", &relationships),
+    ], None)
+}
+
+pub fn write_docx(path: &Path, references: &[ApaFormat]) -> io::Result<()> {
+    fs::write(path, docx(references)?)
+}
+
+/* LibreOffice */
+
+struct OdtSpans;
+impl Backend for OdtSpans {
+    fn plain(&self, text: &str) -> String {
+        escape_xml(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("<text:span text:style-name=\"Italic\">{}</text:span>", escape_xml(text))
+    }
+    fn link(&self, url: &str) -> String {
+        if Url::parse(url).is_err() {
+            return escape_xml(url);
+        }
+        let url = escape_xml(url);
+        format!("<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>", url, url)
+    }
+}
+
+const ODT_MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+const ODT_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.text"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>"#;
+
+pub fn odt(references: &[ApaFormat]) -> io::Result<Vec<u8>> {
+    let mut paragraphs = format!("<text:p text:style-name=\"Heading\">{}</text:p>\n", Lang::of(references).references_heading());
+    for apa in in_reference_order(references) {
+        paragraphs.push_str(&format!("<text:p text:style-name=\"Reference\">{}</text:p>\n", apa.reference().render(&OdtSpans)));
+    }
+
+    // Same layout as the Word version: double spaced with a 0.5 inch hanging indent.
+    let content = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.2">
+<office:automatic-styles>
+<style:style style:name="Heading" style:family="paragraph"><style:paragraph-properties fo:text-align="center" fo:line-height="200%"/><style:text-properties fo:font-family="'Times New Roman'" fo:font-size="12pt" fo:font-weight="bold"/></style:style>
+<style:style style:name="Reference" style:family="paragraph"><style:paragraph-properties fo:margin-left="0.5in" fo:text-indent="-0.5in" fo:line-height="200%"/><style:text-properties fo:font-family="'Times New Roman'" fo:font-size="12pt"/></style:style>
+<style:style style:name="Italic" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+</office:automatic-styles>
+<office:body>
+<office:text>
+{}</office:text>
+</office:body>
+</office:document-content>"#,
+        paragraphs,
+    );
+
+    zip_files(&[
+        ("META-INF/manifest.xml", ODT_MANIFEST),
+        ("content.xml", &content),
+    ], Some(ODT_MIMETYPE))
+}
+
+pub fn write_odt(path: &Path, references: &[ApaFormat]) -> io::Result<()> {
+    fs::write(path, odt(references)?)
+}
+
+// OpenDocument wants the mimetype as the first file, uncompressed.
+fn zip_files(files: &[(&str, &str)], mimetype: Option<&str>) -> io::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    if let Some(mimetype) = mimetype {
+        zip.start_file("mimetype", FileOptions::default().compression_method(CompressionMethod::Stored))?;
+        zip.write_all(mimetype.as_bytes())?;
+    }
+    for (name, content) in files {
+        zip.start_file(*name, FileOptions::default().compression_method(CompressionMethod::Deflated))?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::ApaFormatType;
+
+    fn references() -> Vec<ApaFormat> {
+        let mut journal = ApaFormat::new(ApaFormatType::Journal, Some(Lang::Spanish));
+        journal.set("authors", "Pérez, M.");
+        journal.set("date", "2020");
+        journal.set("title", "Sal & <pimienta>");
+        journal.set("journal", "Cocina");
+        journal.set("doi", "10.1000/xyz");
+
+        // No URL, so the placeholder is printed.
+        let mut website = ApaFormat::new(ApaFormatType::Website, None);
+        website.set("authors", "Smith, J.");
+        website.set("retrieved", "2021-06-01");
+        vec![journal, website]
+    }
+
+    fn unzip(bytes: Vec<u8>, name: &str) -> String {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut content = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    fn text_of(xml: &str, tag: &str) -> Vec<String> {
+        let document = roxmltree::Document::parse(xml).unwrap();
+        document.descendants()
+            .filter(|node| node.tag_name().name() == tag)
+            .map(|node| node.descendants().filter_map(|node| node.text().filter(|_| node.is_text())).collect())
+            .collect()
+    }
+
+    #[test]
+    fn docx_runs() {
+        let bytes = docx(&references()).unwrap();
+        let document = unzip(bytes.clone(), "word/document.xml");
+        let paragraphs = text_of(&document, "p");
+        assert_eq!(paragraphs[0], "Referencias");
+        assert_eq!(paragraphs[1], "Pérez, M. (2020). Sal & <pimienta>. Cocina. https://doi.org/10.1000/xyz");
+        assert!(paragraphs[2].ends_with("from URL"));
+        assert!(document.contains("<w:r><w:rPr><w:i/></w:rPr><w:t xml:space=\"preserve\">Cocina</w:t></w:r>"));
+
+        // Only the DOI is a link.
+        assert_eq!(text_of(&document, "hyperlink"), ["https://doi.org/10.1000/xyz"]);
+        let relationships = unzip(bytes, "word/_rels/document.xml.rels");
+        roxmltree::Document::parse(&relationships).unwrap();
+        assert!(relationships.contains("Target=\"https://doi.org/10.1000/xyz\""));
+        assert!(!relationships.contains("Target=\"URL\""));
+    }
+
+    #[test]
+    fn odt_spans() {
+        let bytes = odt(&references()).unwrap();
+        assert_eq!(unzip(bytes.clone(), "mimetype"), ODT_MIMETYPE);
+        let content = unzip(bytes, "content.xml");
+        let paragraphs = text_of(&content, "p");
+        assert_eq!(paragraphs[0], "Referencias");
+        assert_eq!(paragraphs[1], "Pérez, M. (2020). Sal & <pimienta>. Cocina. https://doi.org/10.1000/xyz");
+        assert!(paragraphs[2].ends_with("from URL"));
+        assert_eq!(text_of(&content, "span"), ["Cocina", "Title the article"]);
+        assert_eq!(text_of(&content, "a"), ["https://doi.org/10.1000/xyz"]);
+    }
+}
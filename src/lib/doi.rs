@@ -0,0 +1,188 @@
+// DOIs as the user pastes them: "doi:10.1037/…", "http://dx.doi.org/10.1037/…" or just "10.1037/…".
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Doi {
+    /// "10.1037", who registered the DOI.
+    pub prefix: String,
+    /// Everything after the first slash.
+    pub suffix: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DoiError {
+    Empty,
+    MissingPrefix,
+    InvalidPrefix(String),
+    MissingSuffix,
+    Whitespace,
+}
+
+impl fmt::Display for DoiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "DOI is empty"),
+            Self::MissingPrefix => write!(f, "DOI should start with 10."),
+            Self::InvalidPrefix(prefix) => write!(f, "\"{}\" is not a DOI prefix", prefix),
+            Self::MissingSuffix => write!(f, "DOI is missing the part after the /"),
+            Self::Whitespace => write!(f, "DOI can't contain spaces"),
+        }
+    }
+}
+
+impl Doi {
+    pub fn parse(input: &str) -> Result<Doi, DoiError> {
+        let mut text = input.trim();
+        if text.is_empty() {
+            return Err(DoiError::Empty);
+        }
+
+        // Remove whatever is in front of the DOI itself.
+        let lower = text.to_lowercase();
+        let mut from_url = false;
+        for prefix in ["https://", "http://"] {
+            if lower.starts_with(prefix) {
+                text = &text[prefix.len()..];
+                from_url = true;
+                break;
+            }
+        }
+        let lower = text.to_lowercase();
+        for prefix in ["www.doi.org/", "dx.doi.org/", "doi.org/", "doi:", "doi "] {
+            if lower.starts_with(prefix) {
+                text = text[prefix.len()..].trim_start();
+                from_url |= prefix.ends_with('/');
+                break;
+            }
+        }
+        // Copied at the end of a sentence.
+        let text = text.trim_end_matches('.');
+
+        // Links have the DOI percent encoded.
+        let text = if from_url { percent_decode(text) } else { text.to_string() };
+
+        if text.contains(char::is_whitespace) {
+            return Err(DoiError::Whitespace);
+        }
+        let (prefix, suffix) = match text.split_once('/') {
+            Some(parts) => parts,
+            None if text.starts_with("10.") => return Err(DoiError::MissingSuffix),
+            None => return Err(DoiError::MissingPrefix),
+        };
+        if !prefix.starts_with("10.") {
+            return Err(DoiError::MissingPrefix);
+        }
+        // The registrant code is made of numbers, with dots for sub-registrants.
+        let registrant = &prefix[3..];
+        if registrant.is_empty()
+            || registrant.split('.').any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit())) {
+            return Err(DoiError::InvalidPrefix(prefix.to_string()));
+        }
+        if suffix.is_empty() {
+            return Err(DoiError::MissingSuffix);
+        }
+
+        Ok(Doi { prefix: prefix.to_string(), suffix: suffix.to_string() })
+    }
+
+    // The form APA 7 wants in the reference.
+    pub fn url(&self) -> String {
+        format!("https://doi.org/{}", percent_encode(&self.to_string()))
+    }
+}
+
+impl fmt::Display for Doi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.prefix, self.suffix)
+    }
+}
+
+// Only the characters that would break the link, DOIs are case insensitive
+// and are meant to be read by people too.
+pub fn percent_encode(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' | '"' | '#' | '<' | '>' | '?' | '\\' | '^' | '`' | '{' | '|' | '}' => {
+                output.push_str(&format!("%{:02X}", c as u32));
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                output.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doi(prefix: &str, suffix: &str) -> Result<Doi, DoiError> {
+        Ok(Doi { prefix: prefix.to_string(), suffix: suffix.to_string() })
+    }
+
+    #[test]
+    fn bare_and_prefixed() {
+        assert_eq!(Doi::parse("10.1037/amp0000722"), doi("10.1037", "amp0000722"));
+        assert_eq!(Doi::parse("doi:10.1037/amp0000722"), doi("10.1037", "amp0000722"));
+        assert_eq!(Doi::parse("DOI: 10.1037/amp0000722."), doi("10.1037", "amp0000722"));
+        assert_eq!(Doi::parse("  10.1000.10/a/b  "), doi("10.1000.10", "a/b"));
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(Doi::parse("https://doi.org/10.1037/amp0000722"), doi("10.1037", "amp0000722"));
+        assert_eq!(Doi::parse("http://dx.doi.org/10.1037/amp0000722"), doi("10.1037", "amp0000722"));
+        assert_eq!(Doi::parse("HTTPS://WWW.DOI.ORG/10.1037/AMP0000722"), doi("10.1037", "AMP0000722"));
+        // Links are percent encoded, the DOI itself isn't.
+        assert_eq!(Doi::parse("https://doi.org/10.1002/%28SICI%291097-4571"), doi("10.1002", "(SICI)1097-4571"));
+        assert_eq!(Doi::parse("10.1002/%28SICI%29"), doi("10.1002", "%28SICI%29"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Doi::parse(" "), Err(DoiError::Empty));
+        assert_eq!(Doi::parse("1037/amp0000722"), Err(DoiError::MissingPrefix));
+        assert_eq!(Doi::parse("amp0000722"), Err(DoiError::MissingPrefix));
+        assert_eq!(Doi::parse("10.abc/amp0000722"), Err(DoiError::InvalidPrefix("10.abc".to_string())));
+        assert_eq!(Doi::parse("10./amp0000722"), Err(DoiError::InvalidPrefix("10.".to_string())));
+        assert_eq!(Doi::parse("10.1037..1/x"), Err(DoiError::InvalidPrefix("10.1037..1".to_string())));
+        assert_eq!(Doi::parse("10.1037"), Err(DoiError::MissingSuffix));
+        assert_eq!(Doi::parse("10.1037/"), Err(DoiError::MissingSuffix));
+        assert_eq!(Doi::parse("10.1037/amp 0000722"), Err(DoiError::Whitespace));
+    }
+
+    #[test]
+    fn urls() {
+        let doi = Doi::parse("10.1002/(SICI)1097-4571#<x>").unwrap();
+        assert_eq!(doi.to_string(), "10.1002/(SICI)1097-4571#<x>");
+        assert_eq!(doi.url(), "https://doi.org/10.1002/(SICI)1097-4571%23%3Cx%3E");
+        assert_eq!(Doi::parse(&doi.url()), Ok(doi));
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("%C3%B1and%C3%BA"), "ñandú");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%2"), "%zz%2");
+    }
+}
@@ -0,0 +1,139 @@
+// Metadata services on the web. Requests go through HttpClient so they can be
+// pointed at a local server, and every base URL can be changed.
+
+use std::{env, fmt, io, time::Duration};
+
+pub trait HttpClient {
+    // The body of a GET request.
+    fn get(&self, url: &str, accept: &str) -> Result<String, FetchError>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FetchError {
+    NotFound,
+    Status(u16),
+    Timeout,
+    Network(String),
+    /// The service answered something that couldn't be read.
+    Invalid(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "not found (404)"),
+            Self::Status(status) => write!(f, "the server answered with an error ({})", status),
+            Self::Timeout => write!(f, "the server took too long to answer"),
+            Self::Network(error) => write!(f, "couldn't connect: {}", error),
+            Self::Invalid(error) => write!(f, "unexpected answer: {}", error),
+        }
+    }
+}
+
+// HTTPS with ureq, what the program uses outside of tests.
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl UreqClient {
+    pub fn new(timeout: Duration) -> UreqClient {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(timeout)
+            .user_agent(concat!("apa-sources-CLI/", env!("CARGO_PKG_VERSION")))
+            .build();
+        UreqClient { agent }
+    }
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn get(&self, url: &str, accept: &str) -> Result<String, FetchError> {
+        match self.agent.get(url).set("Accept", accept).call() {
+            Ok(response) => response.into_string().map_err(|error| FetchError::Invalid(error.to_string())),
+            Err(ureq::Error::Status(404, _)) => Err(FetchError::NotFound),
+            Err(ureq::Error::Status(status, _)) => Err(FetchError::Status(status)),
+            Err(ureq::Error::Transport(transport)) => {
+                let timed_out = std::error::Error::source(&transport)
+                    .and_then(|source| source.downcast_ref::<io::Error>())
+                    .is_some_and(|error| matches!(error.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock));
+                if timed_out {
+                    Err(FetchError::Timeout)
+                } else {
+                    Err(FetchError::Network(transport.to_string()))
+                }
+            }
+        }
+    }
+}
+
+// Base URLs of the services, the public ones unless an environment variable says otherwise.
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+    /// APA_CROSSREF_URL
+    pub crossref: String,
+    /// APA_OPENLIBRARY_URL
+    pub openlibrary: String,
+    /// APA_EUTILS_URL, the directory with efetch.fcgi and esearch.fcgi.
+    pub eutils: String,
+    /// APA_ARXIV_URL
+    pub arxiv: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints {
+            crossref: "https://api.crossref.org".to_string(),
+            openlibrary: "https://openlibrary.org".to_string(),
+            eutils: "https://eutils.ncbi.nlm.nih.gov/entrez/eutils".to_string(),
+            arxiv: "https://export.arxiv.org".to_string(),
+        }
+    }
+}
+
+impl Endpoints {
+    pub fn from_env() -> Endpoints {
+        let default = Endpoints::default();
+        let var = |name: &str, default: String| {
+            env::var(name).ok().filter(|value| !value.trim().is_empty()).unwrap_or(default)
+        };
+        Endpoints {
+            crossref: var("APA_CROSSREF_URL", default.crossref),
+            openlibrary: var("APA_OPENLIBRARY_URL", default.openlibrary),
+            eutils: var("APA_EUTILS_URL", default.eutils),
+            arxiv: var("APA_ARXIV_URL", default.arxiv),
+        }
+    }
+}
+
+// The base URL and the path, without doubled slashes.
+pub fn join(base: &str, path: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+// Recorded answers by URL, anything else is a 404 like it would be for a DOI that doesn't exist.
+#[cfg(test)]
+pub struct FakeClient {
+    pub answers: Vec<(String, String)>,
+}
+
+#[cfg(test)]
+impl FakeClient {
+    pub fn new(answers: &[(&str, &str)]) -> FakeClient {
+        FakeClient { answers: answers.iter().map(|(url, body)| (url.to_string(), body.to_string())).collect() }
+    }
+}
+
+#[cfg(test)]
+impl HttpClient for FakeClient {
+    fn get(&self, url: &str, _accept: &str) -> Result<String, FetchError> {
+        self.answers.iter()
+            .find(|(answer_url, _)| answer_url == url)
+            .map(|(_, body)| body.clone())
+            .ok_or(FetchError::NotFound)
+    }
+}
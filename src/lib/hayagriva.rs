@@ -0,0 +1,358 @@
+// Hayagriva YAML, the bibliography format Typst reads with #bibliography.
+
+use crate::biblatex::unique_keys;
+use crate::date::PublicationDate;
+use crate::locator::PageLocator;
+use crate::person::{parse_people, Person};
+use crate::{ApaFormat, ApaFormatType};
+
+// Entry type of each format and the type of the container it's published in.
+pub fn entry_types(format: ApaFormatType) -> (&'static str, Option<&'static str>) {
+    match format {
+        ApaFormatType::Website => ("web", Some("web")),
+        ApaFormatType::Newspaper => ("article", Some("newspaper")),
+        ApaFormatType::Dictionary => ("entry", Some("reference")),
+        ApaFormatType::Journal => ("article", Some("periodical")),
+        ApaFormatType::Chapter => ("chapter", Some("anthology")),
+        ApaFormatType::Book => ("book", None),
+        ApaFormatType::Preprint => ("article", Some("repository")),
+        ApaFormatType::None => ("misc", None),
+    }
+}
+
+// Lines of YAML, indented by two spaces per level.
+struct Yaml {
+    lines: Vec<String>,
+}
+impl Yaml {
+    fn value(&mut self, indent: usize, key: &str, value: &str) {
+        if !value.trim().is_empty() {
+            self.lines.push(format!("{}{}: {}", "  ".repeat(indent), key, quote(value.trim())));
+        }
+    }
+    fn people(&mut self, indent: usize, key: &str, people: &[Person]) {
+        if people.is_empty() {
+            return;
+        }
+        let names: Vec<String> = people.iter().map(|person| quote(&hayagriva_name(person))).collect();
+        self.lines.push(format!("{}{}: [{}]", "  ".repeat(indent), key, names.join(", ")));
+    }
+    fn key(&mut self, indent: usize, key: &str) {
+        self.lines.push(format!("{}{}:", "  ".repeat(indent), key));
+    }
+}
+
+pub fn entry(apa: &ApaFormat, key: &str) -> String {
+    let (entry_type, parent_type) = entry_types(apa.format);
+    let mut yaml = Yaml { lines: vec![format!("{}:", key)] };
+
+    yaml.value(1, "type", entry_type);
+    let title = if apa.format == ApaFormatType::Dictionary { apa.get("word") } else { apa.get("title") };
+    yaml.value(1, "title", title);
+    yaml.people(1, "author", &parse_people(apa.get("authors")));
+    if let Some(date) = PublicationDate::parse(apa.get("date")) {
+        yaml.value(1, "date", &date.iso());
+    }
+
+    match PageLocator::parse(apa.get("pages")) {
+        Some((PageLocator::Single(page), _)) => yaml.value(1, "page-range", &page),
+        Some((PageLocator::Range(start, end), _)) => yaml.value(1, "page-range", &format!("{}-{}", start, end)),
+        _ => {}
+    }
+
+    // DOIs and article numbers are both serial numbers.
+    let article = match PageLocator::parse(apa.get("pages")) {
+        Some((PageLocator::Article(number), _)) => Some(number),
+        _ => None,
+    };
+    if apa.doi().is_some() || article.is_some() {
+        yaml.key(1, "serial-number");
+        if let Some(doi) = apa.doi() {
+            yaml.value(2, "doi", &doi.to_string());
+        }
+        if let Some(number) = article {
+            yaml.value(2, "article", &number);
+        }
+    }
+
+    // Online sources keep the date they were consulted with the URL.
+    if !apa.get("url").is_empty() {
+        yaml.key(1, "url");
+        yaml.value(2, "value", &apa.url());
+        if matches!(apa.format, ApaFormatType::Website | ApaFormatType::Newspaper | ApaFormatType::Dictionary) {
+            yaml.value(2, "date", &apa.retrieved().iso());
+        }
+    }
+
+    // The newspaper, dictionary, website, journal or book it's part of.
+    if let Some(parent_type) = parent_type {
+        let container = apa.format.container().map(|id| apa.get(id)).unwrap_or("");
+        yaml.key(1, "parent");
+        yaml.value(2, "type", parent_type);
+        yaml.value(2, "title", container);
+        yaml.people(2, "editor", &parse_people(apa.get("editors")));
+        yaml.value(2, "volume", apa.get("volume"));
+        yaml.value(2, "issue", apa.get("issue"));
+        yaml.value(2, "publisher", apa.get("publisher"));
+    }
+
+    // Books are their own parent, their publisher goes on them.
+    if apa.format == ApaFormatType::Book {
+        yaml.value(1, "edition", apa.get("edition"));
+        yaml.value(1, "publisher", apa.get("publisher"));
+    }
+
+    yaml.lines.join("\n") + "\n"
+}
+
+pub fn file(references: &[ApaFormat]) -> String {
+    unique_keys(references).iter()
+        .zip(references)
+        .map(|(key, apa)| entry(apa, key))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// "Last, First", organizations as they are.
+fn hayagriva_name(person: &Person) -> String {
+    if person.is_organization() {
+        person.family.clone()
+    } else {
+        format!("{}, {}", person.family, person.given)
+    }
+}
+
+// Double quoted YAML string, so nothing the user typed is read as YAML syntax.
+fn quote(text: &str) -> String {
+    let mut output = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            _ if c.is_control() => {}
+            _ => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lang;
+
+    fn yaml(format: ApaFormatType, fields: &[(&str, &str)]) -> String {
+        let mut apa = ApaFormat::new(format, Some(Lang::English));
+        for (id, value) in fields {
+            assert!(apa.set(id, *value), "{} has no {}", format, id);
+        }
+        entry(&apa, "key")
+    }
+
+    #[test]
+    fn website() {
+        assert_eq!(
+            yaml(ApaFormatType::Website, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020, May 4"),
+                ("title", "Reading: a guide #1"),
+                ("website", "City Library"),
+                ("url", "https://example.org/rooms#hours"),
+                ("retrieved", "2021-06-01"),
+            ]),
+            "key:
+  type: \"web\"
+  title: \"Reading: a guide #1\"
+  author: [\"Smith, J. A.\"]
+  date: \"2020-05-04\"
+  url:
+    value: \"https://example.org/rooms#hours\"
+    date: \"2021-06-01\"
+  parent:
+    type: \"web\"
+    title: \"City Library\"
+",
+        );
+    }
+
+    #[test]
+    fn newspaper() {
+        assert_eq!(
+            yaml(ApaFormatType::Newspaper, &[
+                ("authors", "World Health Organization"),
+                ("date", "2020, March 11"),
+                ("title", "The \"pandemic\" is declared"),
+                ("newspaper", "The Daily"),
+                ("doi", "10.1000/xyz"),
+            ]),
+            "key:
+  type: \"article\"
+  title: \"The \\\"pandemic\\\" is declared\"
+  author: [\"World Health Organization\"]
+  date: \"2020-03-11\"
+  serial-number:
+    doi: \"10.1000/xyz\"
+  parent:
+    type: \"newspaper\"
+    title: \"The Daily\"
+",
+        );
+    }
+
+    #[test]
+    fn dictionary() {
+        assert_eq!(
+            yaml(ApaFormatType::Dictionary, &[
+                ("authors", "Merriam-Webster"),
+                ("date", "n.d."),
+                ("word", "Reference"),
+                ("editors", "A. Editor"),
+                ("dictionary", "Merriam-Webster.com dictionary"),
+                ("publisher", "Merriam-Webster"),
+                ("url", "https://www.merriam-webster.com/dictionary/reference"),
+                ("retrieved", "2021-06-01"),
+            ]),
+            "key:
+  type: \"entry\"
+  title: \"Reference\"
+  author: [\"Merriam-Webster\"]
+  url:
+    value: \"https://www.merriam-webster.com/dictionary/reference\"
+    date: \"2021-06-01\"
+  parent:
+    type: \"reference\"
+    title: \"Merriam-Webster.com dictionary\"
+    editor: [\"Editor, A.\"]
+    publisher: \"Merriam-Webster\"
+",
+        );
+    }
+
+    #[test]
+    fn journal() {
+        assert_eq!(
+            yaml(ApaFormatType::Journal, &[
+                ("authors", "Smith, J. A., & Pérez, M."),
+                ("date", "2020"),
+                ("title", "Memory: a study"),
+                ("journal", "Journal of Things"),
+                ("volume", "12"),
+                ("issue", "3"),
+                ("pages", "123-45"),
+                ("doi", "10.1000/xyz"),
+            ]),
+            "key:
+  type: \"article\"
+  title: \"Memory: a study\"
+  author: [\"Smith, J. A.\", \"Pérez, M.\"]
+  date: \"2020\"
+  page-range: \"123-145\"
+  serial-number:
+    doi: \"10.1000/xyz\"
+  parent:
+    type: \"periodical\"
+    title: \"Journal of Things\"
+    volume: \"12\"
+    issue: \"3\"
+",
+        );
+
+        // Article numbers instead of pages.
+        let yaml = yaml(ApaFormatType::Journal, &[("title", "Online"), ("pages", "e0123456")]);
+        assert!(yaml.contains("  serial-number:\n    article: \"e0123456\"\n"));
+        assert!(!yaml.contains("page-range"));
+    }
+
+    #[test]
+    fn chapter() {
+        assert_eq!(
+            yaml(ApaFormatType::Chapter, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A chapter"),
+                ("editors", "A. Editor & B. Editor"),
+                ("book", "Handbook: second part"),
+                ("pages", "45-67"),
+                ("publisher", "Press"),
+            ]),
+            "key:
+  type: \"chapter\"
+  title: \"A chapter\"
+  author: [\"Smith, J. A.\"]
+  date: \"2020\"
+  page-range: \"45-67\"
+  parent:
+    type: \"anthology\"
+    title: \"Handbook: second part\"
+    editor: [\"Editor, A.\", \"Editor, B.\"]
+    publisher: \"Press\"
+",
+        );
+    }
+
+    #[test]
+    fn book() {
+        assert_eq!(
+            yaml(ApaFormatType::Book, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "C:\\ and you"),
+                ("edition", "2"),
+                ("publisher", "Press"),
+                ("url", "https://example.org/book"),
+            ]),
+            "key:
+  type: \"book\"
+  title: \"C:\\\\ and you\"
+  author: [\"Smith, J. A.\"]
+  date: \"2020\"
+  url:
+    value: \"https://example.org/book\"
+  edition: \"2\"
+  publisher: \"Press\"
+",
+        );
+    }
+
+    #[test]
+    fn preprint() {
+        assert_eq!(
+            yaml(ApaFormatType::Preprint, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A preprint"),
+                ("archive", "arXiv"),
+                ("url", "https://arxiv.org/abs/2001.00001v2"),
+            ]),
+            "key:
+  type: \"article\"
+  title: \"A preprint\"
+  author: [\"Smith, J. A.\"]
+  date: \"2020\"
+  url:
+    value: \"https://arxiv.org/abs/2001.00001v2\"
+  parent:
+    type: \"repository\"
+    title: \"arXiv\"
+",
+        );
+    }
+
+    #[test]
+    fn files_have_unique_keys() {
+        let mut apa = ApaFormat::new(ApaFormatType::Book, None);
+        apa.set("authors", "Smith, J.");
+        apa.set("date", "2020");
+        apa.set("title", "Memory");
+        let mut other = ApaFormat::new(ApaFormatType::Book, None);
+        other.set("authors", "Smith, J.");
+        other.set("date", "2020");
+        other.set("title", "Memory\tand more");
+        let file = file(&[apa, other]);
+        assert!(file.starts_with("smith2020memorya:\n"));
+        assert!(file.contains("\n\nsmith2020memoryb:\n  type: \"book\"\n  title: \"Memory\\tand more\"\n"));
+    }
+}
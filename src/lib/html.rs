@@ -0,0 +1,98 @@
+// Escaped HTML references, for the clipboard, web pages and standalone files.
+
+use std::{fs, io, path::Path};
+
+use crate::output::in_reference_order;
+use crate::rich::{escape_html, Html};
+use crate::{ApaFormat, Lang};
+
+// Double spaced with a 0.5 inch hanging indent. Inline so it survives pasting.
+const REFERENCE_STYLE: &str = "margin: 0; padding-left: 0.5in; text-indent: -0.5in; line-height: 2;";
+
+pub fn reference(apa: &ApaFormat) -> String {
+    apa.reference().render(&Html)
+}
+
+// What goes into the clipboard, a small but valid document so the charset is known.
+pub fn clipboard(apa: &ApaFormat) -> String {
+    format!("<!DOCTYPE html><html><head><meta charset=\"utf-8\"></head><body>{}</body></html>", reference(apa))
+}
+
+// The references in order, each one in its own paragraph.
+pub fn reference_list(references: &[ApaFormat]) -> String {
+    let mut output = format!(
+        "<section class=\"references\">\n<h1 style=\"text-align: center; font-size: 1em;\">{}</h1>\n",
+        escape_html(Lang::of(references).references_heading()),
+    );
+    for apa in in_reference_order(references) {
+        output.push_str(&format!("<p class=\"reference\" style=\"{}\">{}</p>\n", REFERENCE_STYLE, reference(apa)));
+    }
+    output.push_str("</section>\n");
+    output
+}
+
+// A whole page that can be opened in a browser.
+pub fn document(references: &[ApaFormat]) -> String {
+    let lang = match Lang::of(references) {
+        Lang::Spanish => "es",
+        Lang::English => "en",
+    };
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\nbody {{ font-family: \"Times New Roman\", serif; font-size: 12pt; max-width: 6.5in; margin: 1in auto; }}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        lang,
+        escape_html(Lang::of(references).references_heading()),
+        reference_list(references),
+    )
+}
+
+pub fn write_file(path: &Path, references: &[ApaFormat]) -> io::Result<()> {
+    fs::write(path, document(references))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApaFormatType;
+
+    fn website(lang: Lang) -> ApaFormat {
+        let mut apa = ApaFormat::new(ApaFormatType::Website, Some(lang));
+        apa.set("authors", "Smith & Sons");
+        apa.set("date", "2020");
+        apa.set("title", "<b>Bold</b> \"claims\"");
+        apa.set("website", "R&D");
+        apa.set("url", "https://example.org/search?q=\"a\"&page=2");
+        apa.set("retrieved", "2021-06-01");
+        apa
+    }
+
+    #[test]
+    fn references_are_escaped() {
+        let html = reference(&website(Lang::English));
+        assert_eq!(
+            html,
+            "Smith &amp; Sons. (2020). <i>&lt;b&gt;Bold&lt;/b&gt; &quot;claims&quot;</i>. R&amp;D. Retrived June 1, 2021, from \
+             <a href=\"https://example.org/search?q=&quot;a&quot;&amp;page=2\">https://example.org/search?q=&quot;a&quot;&amp;page=2</a>",
+        );
+        // Placeholders aren't links.
+        let empty = ApaFormat::new(ApaFormatType::Website, None);
+        assert!(!reference(&empty).contains("<a "));
+    }
+
+    #[test]
+    fn documents_take_the_language_of_the_references() {
+        let page = document(&[website(Lang::Spanish), website(Lang::English)]);
+        assert!(page.contains("<html lang=\"es\">"));
+        assert!(page.contains("<title>Referencias</title>"));
+        assert!(page.contains(">Referencias</h1>"));
+        assert_eq!(page.matches("<p class=\"reference\"").count(), 2);
+        assert!(page.contains("R&amp;D"));
+        assert!(!page.contains("<b>"));
+
+        let page = document(&[website(Lang::English)]);
+        assert!(page.contains("<html lang=\"en\">"));
+        assert!(page.contains("<title>References</title>"));
+
+        // Nothing to take it from.
+        assert!(document(&[]).contains("<html lang=\"en\">"));
+    }
+}
@@ -0,0 +1,135 @@
+// References made by other programs, opened so they can be edited here.
+
+use std::{fs, path::Path};
+
+use crate::arxiv::ArxivId;
+use crate::doi::Doi;
+use crate::fetch::{Endpoints, HttpClient};
+use crate::isbn::Isbn;
+use crate::pmid::PubmedId;
+use crate::url::Url;
+use crate::{arxiv, bibtex, crossref, csl, openlibrary, pubmed, reverse, ris, webpage, zotero, ApaFormat, ApaFormatType, Lang, PromptKind};
+
+// A reference found in a file, before the user picks it.
+pub struct Candidate {
+    /// What the picker shows, the key or id and the reference.
+    pub label: String,
+    pub apa: ApaFormat,
+    /// What didn't fit in the format, shown in the editor.
+    pub warnings: Vec<String>,
+    /// Ids of the fields that were guessed, marked until they are edited.
+    pub review: Vec<&'static str>,
+}
+
+impl Candidate {
+    pub fn new(key: &str, apa: ApaFormat, warnings: Vec<String>) -> Candidate {
+        let label = if key.is_empty() { apa.to_string() } else { format!("[{}] {}", key, apa) };
+        Candidate { label, apa, warnings, review: Vec::new() }
+    }
+}
+
+// Every reference in the file, the format is told by the extension.
+pub fn open_file(path: &Path, lang: Lang) -> Result<Vec<Candidate>, String> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let text = fs::read_to_string(path).map_err(|error| format!("Couldn't open {}: {}", path.display(), error))?;
+
+    let candidates = match extension.as_str() {
+        "bib" | "bibtex" => bibtex::candidates(&text, lang),
+        "ris" => ris::candidates(&text, lang),
+        "json" => csl::candidates(&text, lang),
+        "html" | "htm" => {
+            let (apa, warnings) = webpage::from_html(&text, None, lang);
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+        _ => Err(format!("Can't open .{} files, only .bib, .ris, CSL .json and .html", extension)),
+    }
+    .map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    if candidates.is_empty() {
+        return Err(format!("There are no references in {}", path.display()));
+    }
+    Ok(candidates)
+}
+
+// What the user typed in a prompt, looked up.
+pub fn prompt(kind: PromptKind, input: &str, lang: Lang, client: &dyn HttpClient, endpoints: &Endpoints) -> Result<Vec<Candidate>, String> {
+    let input = input.trim();
+    match kind {
+        PromptKind::OpenFile => open_file(Path::new(input), lang),
+        PromptKind::Doi => {
+            let doi = Doi::parse(input).map_err(|error| error.to_string())?;
+            let (apa, warnings) = crossref::lookup(client, &endpoints.crossref, &doi, lang)
+                .map_err(|error| format!("Couldn't fetch {}: {}", doi, error))?;
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+        PromptKind::Isbn => {
+            let isbn = Isbn::parse(input).map_err(|error| error.to_string())?;
+            let (apa, warnings) = openlibrary::lookup(client, &endpoints.openlibrary, &isbn, lang)
+                .map_err(|error| format!("Couldn't fetch ISBN {}: {}", isbn, error))?;
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+        // Saved pages work too, so they can be read offline.
+        PromptKind::Url if !input.contains("://") => open_file(Path::new(input), lang),
+        PromptKind::Url => {
+            let url = Url::parse(input).map_err(|error| error.to_string())?;
+            let (apa, warnings) = webpage::lookup(client, &url, lang)
+                .map_err(|error| format!("Couldn't fetch {}: {}", url, error))?;
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+        PromptKind::Pubmed => {
+            let id = PubmedId::parse(input).map_err(|error| error.to_string())?;
+            let (apa, warnings) = pubmed::lookup(client, &endpoints.eutils, &id, lang)
+                .map_err(|error| format!("Couldn't fetch {}: {}", id, error))?;
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+        PromptKind::Preprint => preprint(input, lang, client, endpoints),
+        PromptKind::Citation if input.is_empty() => Err("Paste a reference first".to_string()),
+        PromptKind::Citation => {
+            let parsed = reverse::parse(input, lang);
+            let mut candidate = Candidate::new("", parsed.apa, parsed.warnings);
+            candidate.review = parsed.review;
+            Ok(vec![candidate])
+        }
+        // An empty search lists the whole library.
+        PromptKind::Zotero => zotero::search(&zotero::default_path(), input, lang),
+    }
+}
+
+// The preprint, and the article it became when the archive knows it, so the user can cite that one.
+fn preprint(input: &str, lang: Lang, client: &dyn HttpClient, endpoints: &Endpoints) -> Result<Vec<Candidate>, String> {
+    let (apa, mut warnings, published) = match ArxivId::parse(input) {
+        Ok(id) => arxiv::lookup(client, &endpoints.arxiv, &id, lang)
+            .map_err(|error| format!("Couldn't fetch arXiv:{}: {}", id, error))?,
+        // bioRxiv, PsyArXiv... are in Crossref.
+        Err(error) => {
+            let doi = Doi::parse(input).map_err(|_| error.to_string())?;
+            let work = crossref::fetch_work(client, &endpoints.crossref, &doi)
+                .map_err(|error| format!("Couldn't fetch {}: {}", doi, error))?;
+            let (apa, mut warnings) = crossref::from_work(&work, lang);
+            if apa.format != ApaFormatType::Preprint {
+                warnings.push(format!("{} isn't a preprint, it was opened as a {}", doi, apa.format));
+            }
+            (apa, warnings, crossref::published_version(&work))
+        }
+    };
+
+    let Some(published) = published else {
+        return Ok(vec![Candidate::new("", apa, warnings)]);
+    };
+    match crossref::lookup(client, &endpoints.crossref, &published, lang) {
+        Ok((published_apa, published_warnings)) => {
+            warnings.push("it was published, APA prefers citing the published version if you read it".to_string());
+            Ok(vec![
+                Candidate::new("preprint", apa, warnings),
+                Candidate::new("published", published_apa, published_warnings),
+            ])
+        }
+        Err(error) => {
+            warnings.push(format!("it was published as {}, but it couldn't be fetched: {}", published.url(), error));
+            Ok(vec![Candidate::new("", apa, warnings)])
+        }
+    }
+}
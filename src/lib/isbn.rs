@@ -0,0 +1,176 @@
+// ISBNs as printed on books: "ISBN 978-0-13-110362-7", "0131103628" or with an X at the end.
+
+use std::fmt;
+
+// Always kept as the 13 digits, ISBN-10s are converted when they are parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Isbn {
+    digits: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IsbnError {
+    Empty,
+    InvalidCharacter(char),
+    Length(usize),
+    Checksum,
+    /// ISBN-13s start with 978 or 979.
+    Prefix,
+}
+
+impl fmt::Display for IsbnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "ISBN is empty"),
+            Self::InvalidCharacter(c) => write!(f, "ISBN can't contain \"{}\"", c),
+            Self::Length(length) => write!(f, "ISBN should have 10 or 13 digits, not {}", length),
+            Self::Checksum => write!(f, "ISBN check digit is wrong, there is a typo"),
+            Self::Prefix => write!(f, "ISBN-13 should start with 978 or 979"),
+        }
+    }
+}
+
+impl Isbn {
+    pub fn parse(input: &str) -> Result<Isbn, IsbnError> {
+        let mut text = input.trim();
+        // "ISBN-13: ..." or "ISBN 978...", the label isn't part of the number.
+        if text.get(..4).is_some_and(|label| label.eq_ignore_ascii_case("isbn")) {
+            text = text[4..].trim_start_matches(['-', ' ']);
+            for length in ["13", "10"] {
+                if let Some(rest) = text.strip_prefix(length).filter(|rest| rest.starts_with([':', ' '])) {
+                    text = rest;
+                }
+            }
+            text = text.trim_start_matches([':', ' ']);
+        }
+        if text.is_empty() {
+            return Err(IsbnError::Empty);
+        }
+
+        let mut digits = String::new();
+        for c in text.chars() {
+            match c {
+                '0'..='9' => digits.push(c),
+                // The check digit of ISBN-10s can be 10.
+                'X' | 'x' if digits.len() == 9 => digits.push('X'),
+                '-' | ' ' => {}
+                _ => return Err(IsbnError::InvalidCharacter(c)),
+            }
+        }
+
+        match digits.len() {
+            10 => {
+                if isbn10_check(&digits[..9]) != digits.chars().last().unwrap() {
+                    return Err(IsbnError::Checksum);
+                }
+                Ok(Isbn { digits: to_isbn13(&digits[..9]) })
+            }
+            13 => {
+                if !digits.starts_with("978") && !digits.starts_with("979") {
+                    return Err(IsbnError::Prefix);
+                }
+                if isbn13_check(&digits[..12]) != digits.chars().last().unwrap() {
+                    return Err(IsbnError::Checksum);
+                }
+                Ok(Isbn { digits })
+            }
+            length => Err(IsbnError::Length(length)),
+        }
+    }
+
+    pub fn isbn13(&self) -> &str {
+        &self.digits
+    }
+
+    // Only books with a 978 ISBN-13 have an ISBN-10.
+    pub fn isbn10(&self) -> Option<String> {
+        let body = self.digits.strip_prefix("978")?;
+        let body = &body[..9];
+        Some(format!("{}{}", body, isbn10_check(body)))
+    }
+}
+
+impl fmt::Display for Isbn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.digits)
+    }
+}
+
+// "978" and the first 9 digits of the ISBN-10, with the new check digit.
+pub fn to_isbn13(isbn10_body: &str) -> String {
+    let body = format!("978{}", isbn10_body);
+    let check = isbn13_check(&body);
+    format!("{}{}", body, check)
+}
+
+// Weights 10 to 2, the check digit makes it a multiple of 11.
+fn isbn10_check(body: &str) -> char {
+    let sum: u32 = body.chars()
+        .filter_map(|c| c.to_digit(10))
+        .zip((2..=10).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10).unwrap(),
+    }
+}
+
+// Weights 1 and 3, the check digit makes it a multiple of 10.
+fn isbn13_check(body: &str) -> char {
+    let sum: u32 = body.chars()
+        .filter_map(|c| c.to_digit(10))
+        .zip([1, 3].iter().cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isbn13() {
+        let isbn = Isbn::parse("ISBN 978-0-13-110362-7").unwrap();
+        assert_eq!(isbn.isbn13(), "9780131103627");
+        assert_eq!(isbn.isbn10().as_deref(), Some("0131103628"));
+        assert_eq!(Isbn::parse("ISBN-13: 978 0 13 110362 7"), Ok(isbn));
+    }
+
+    #[test]
+    fn isbn10_is_converted() {
+        assert_eq!(Isbn::parse("0-13-110362-8").unwrap().isbn13(), "9780131103627");
+        assert_eq!(Isbn::parse("ISBN-10: 0131103628").unwrap().to_string(), "9780131103627");
+    }
+
+    #[test]
+    fn x_check_digit() {
+        let isbn = Isbn::parse("0-8044-2957-x").unwrap();
+        assert_eq!(isbn.isbn13(), "9780804429573");
+        assert_eq!(isbn.isbn10().as_deref(), Some("080442957X"));
+        // Only the last digit of an ISBN-10 can be an X.
+        assert_eq!(Isbn::parse("08044X9571"), Err(IsbnError::InvalidCharacter('X')));
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(Isbn::parse("9780131103628"), Err(IsbnError::Checksum));
+        assert_eq!(Isbn::parse("0131103627"), Err(IsbnError::Checksum));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Isbn::parse("  "), Err(IsbnError::Empty));
+        assert_eq!(Isbn::parse("ISBN: "), Err(IsbnError::Empty));
+        assert_eq!(Isbn::parse("12345"), Err(IsbnError::Length(5)));
+        assert_eq!(Isbn::parse("0131103628a"), Err(IsbnError::InvalidCharacter('a')));
+        assert_eq!(Isbn::parse("9770131103627"), Err(IsbnError::Prefix));
+    }
+
+    #[test]
+    fn only_978_has_an_isbn10() {
+        let isbn = Isbn::parse("979-10-90636-07-1").unwrap();
+        assert_eq!(isbn.isbn10(), None);
+    }
+}
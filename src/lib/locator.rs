@@ -0,0 +1,180 @@
+// Pages of journal articles and book chapters, "12-34", "pp 12 - 34", "e0123456"...
+
+use crate::ApaFormatType;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PageLocator {
+    Single(String),
+    Range(String, String),
+    /// Online journals that use article numbers (e-locators) instead of pages.
+    Article(String),
+}
+
+impl PageLocator {
+    // Parse whatever the user typed, returns the locator and the warnings about it.
+    pub fn parse(input: &str) -> Option<(PageLocator, Vec<String>)> {
+        let mut warnings = Vec::new();
+
+        // Remove "pp.", "p." and "Article", they are added back when formatting.
+        let mut text = input.trim();
+        let mut article = false;
+        let lower = text.to_lowercase();
+        for prefix in ["article", "pages", "page", "pp.", "pp", "p.", "p "] {
+            if lower.starts_with(prefix) {
+                article = prefix == "article";
+                text = text[prefix.len()..].trim_start_matches(['.', ' ']);
+                break;
+            }
+        }
+        if text.is_empty() {
+            return None;
+        }
+
+        // Any kind of dash separates the range.
+        let parts: Vec<&str> = text.split(['-', '–', '—', '‐', '‑'])
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect();
+
+        let locator = match parts.as_slice() {
+            [single] if article || is_e_locator(single) => PageLocator::Article(single.to_string()),
+            [single] => PageLocator::Single(single.to_string()),
+            [start, end] => {
+                let end = expand_end(start, end);
+                if let (Some(first), Some(last)) = (page_number(start), page_number(&end)) {
+                    if first > last {
+                        warnings.push(format!("page range {}–{} is reversed", start, end));
+                    }
+                }
+                PageLocator::Range(start.to_string(), end)
+            }
+            _ => {
+                warnings.push(format!("\"{}\" is not a page range", input.trim()));
+                PageLocator::Single(text.to_string())
+            }
+        };
+
+        // Roman numerals are fine for front matter, anything else is probably a typo.
+        let pages = match &locator {
+            PageLocator::Single(page) => vec![page],
+            PageLocator::Range(start, end) => vec![start, end],
+            PageLocator::Article(_) => vec![],
+        };
+        for page in pages {
+            if page_number(page).is_none() && !is_roman(page) {
+                warnings.push(format!("page \"{}\" is not a number", page));
+            }
+        }
+
+        Some((locator, warnings))
+    }
+
+    // APA form of the pages, chapters are the only ones that keep "pp.".
+    pub fn format(&self, format: ApaFormatType) -> String {
+        let chapter = format == ApaFormatType::Chapter;
+        match self {
+            PageLocator::Single(page) if chapter => format!("p. {}", page),
+            PageLocator::Single(page) => page.clone(),
+            PageLocator::Range(start, end) if chapter => format!("pp. {}–{}", start, end),
+            PageLocator::Range(start, end) => format!("{}–{}", start, end),
+            PageLocator::Article(number) => format!("Article {}", number),
+        }
+    }
+}
+
+// e0123456, the e-locators of PLOS and friends.
+fn is_e_locator(page: &str) -> bool {
+    let mut chars = page.chars();
+    matches!(chars.next(), Some('e') | Some('E'))
+        && page.len() > 2
+        && chars.all(|c| c.is_ascii_digit())
+}
+
+// The number of a page, ignoring prefixes like the S in "S12".
+fn page_number(page: &str) -> Option<u32> {
+    let digits = page.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn is_roman(page: &str) -> bool {
+    !page.is_empty() && page.chars().all(|c| "ivxlcdmIVXLCDM".contains(c))
+}
+
+// "123-45" means 123–145, APA wants both numbers complete.
+pub fn expand_end(start: &str, end: &str) -> String {
+    let all_digits = |page: &str| !page.is_empty() && page.chars().all(|c| c.is_ascii_digit());
+    if all_digits(start) && all_digits(end) && end.len() < start.len() {
+        format!("{}{}", &start[..start.len() - end.len()], end)
+    } else {
+        end.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (PageLocator, Vec<String>) {
+        PageLocator::parse(input).unwrap()
+    }
+
+    fn range(start: &str, end: &str) -> PageLocator {
+        PageLocator::Range(start.to_string(), end.to_string())
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse("123-45"), (range("123", "145"), vec![]));
+        assert_eq!(parse("12 – 34"), (range("12", "34"), vec![]));
+        assert_eq!(parse("S12–S19"), (range("S12", "S19"), vec![]));
+        assert_eq!(parse("xii-xiv"), (range("xii", "xiv"), vec![]));
+        assert_eq!(parse("123-45").0.format(ApaFormatType::Journal), "123–145");
+    }
+
+    #[test]
+    fn prefixes_are_removed() {
+        assert_eq!(parse("pp. 12-34").0, range("12", "34"));
+        assert_eq!(parse("pp 12-34").0, range("12", "34"));
+        assert_eq!(parse("p. 7").0, PageLocator::Single("7".to_string()));
+        assert_eq!(parse("p 7").0, PageLocator::Single("7".to_string()));
+        assert_eq!(parse("Pages 7-9").0, range("7", "9"));
+        assert_eq!(PageLocator::parse("pp."), None);
+        assert_eq!(PageLocator::parse("  "), None);
+    }
+
+    #[test]
+    fn article_numbers() {
+        assert_eq!(parse("e0123456").0, PageLocator::Article("e0123456".to_string()));
+        assert_eq!(parse("Article 42").0, PageLocator::Article("42".to_string()));
+        assert_eq!(parse("e0123456").0.format(ApaFormatType::Journal), "Article e0123456");
+        // Too short to be an e-locator.
+        assert_eq!(parse("e1").0, PageLocator::Single("e1".to_string()));
+    }
+
+    #[test]
+    fn chapters_keep_pp() {
+        assert_eq!(parse("45-67").0.format(ApaFormatType::Chapter), "pp. 45–67");
+        assert_eq!(parse("45").0.format(ApaFormatType::Chapter), "p. 45");
+        assert_eq!(parse("45").0.format(ApaFormatType::Journal), "45");
+    }
+
+    #[test]
+    fn warnings() {
+        assert_eq!(parse("34-12").1, ["page range 34–12 is reversed"]);
+        let (locator, warnings) = parse("12-34-56");
+        assert_eq!(locator, PageLocator::Single("12-34-56".to_string()));
+        assert_eq!(warnings, ["\"12-34-56\" is not a page range", "page \"12-34-56\" is not a number"]);
+        assert_eq!(parse("12a").1, ["page \"12a\" is not a number"]);
+    }
+
+    #[test]
+    fn ends_are_completed() {
+        assert_eq!(expand_end("123", "45"), "145");
+        assert_eq!(expand_end("1054", "62"), "1062");
+        assert_eq!(expand_end("12", "345"), "345");
+        assert_eq!(expand_end("S12", "9"), "9");
+    }
+}
@@ -0,0 +1,21 @@
+// Pandoc flavoured Markdown, for drafts written in Markdown.
+
+use crate::output::in_reference_order;
+use crate::rich::Markdown;
+use crate::{ApaFormat, Lang};
+
+pub fn reference(apa: &ApaFormat) -> String {
+    apa.reference().render(&Markdown)
+}
+
+// A whole "References" section. Pandoc gives the refs div a hanging indent,
+// like APA wants the reference list.
+pub fn references_section(references: &[ApaFormat]) -> String {
+    let heading = Lang::of(references).references_heading();
+    let mut output = format!("# {} {{-}}\n\n::: {{#refs .references .hanging-indent}}\n", heading);
+    for apa in in_reference_order(references) {
+        output.push_str(&format!("{}\n\n", reference(apa)));
+    }
+    output.push_str(":::\n");
+    output
+}
@@ -1,23 +1,77 @@
-use std::{fmt::{self, format}, thread, time::Duration};
-use std::{collections::HashMap, slice::Iter};
+use std::fmt;
 
-use date::retrive_current_date;
-use termion::style;
+use date::{retrieved_on, today, PublicationDate};
+use doi::Doi;
+use import::Candidate;
+use locator::PageLocator;
+use output::OutputFormat;
+use person::parse_people;
+use rich::RichText;
+use schema::FieldDef;
+use termion::event::Key;
+use url::Url;
 use x11_clipboard::Clipboard;
 
+pub mod arxiv;
+pub mod biblatex;
+pub mod bibtex;
+pub mod crossref;
+pub mod csl;
 pub mod date;
+pub mod document;
+pub mod doi;
+pub mod fetch;
+pub mod hayagriva;
+pub mod html;
+pub mod import;
+pub mod isbn;
+pub mod locator;
+pub mod markdown;
+pub mod openlibrary;
+pub mod output;
+pub mod person;
+pub mod pmid;
+pub mod pubmed;
+pub mod reverse;
+pub mod rich;
+pub mod ris;
+pub mod rtf;
+pub mod schema;
+pub mod url;
+pub mod webpage;
+pub mod zotero;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ApaFormatType {
     None,
     Website,
     Newspaper,
     Dictionary,
+    Journal,
+    Chapter,
+    Book,
+    Preprint,
 }
 impl ApaFormatType {
     // Used for the generation of the format list.
-    pub fn list() -> [ApaFormatType; 3] {
-        [ApaFormatType::Website, ApaFormatType::Newspaper, ApaFormatType::Dictionary]
+    pub fn list() -> [ApaFormatType; 7] {
+        [ApaFormatType::Website, ApaFormatType::Newspaper, ApaFormatType::Dictionary, ApaFormatType::Journal, ApaFormatType::Chapter, ApaFormatType::Book, ApaFormatType::Preprint]
+    }
+    // Name used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Website => "website",
+            Self::Newspaper => "newspaper",
+            Self::Dictionary => "dictionary",
+            Self::Journal => "journal",
+            Self::Chapter => "chapter",
+            Self::Book => "book",
+            Self::Preprint => "preprint",
+            Self::None => "none",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<ApaFormatType> {
+        Self::list().into_iter().find(|format| format.name() == name)
     }
     pub fn link(&self) -> &'static str {
         // Provide the link with more information about the format.
@@ -25,9 +79,38 @@ impl ApaFormatType {
             Self::Website => "https://www.scribbr.com/apa-examples/website/",
             Self::Newspaper => "https://www.scribbr.com/apa-examples/website/",
             Self::Dictionary =>  "https://www.scribbr.com/apa-examples/website/",
+            Self::Journal => "https://www.scribbr.com/apa-examples/journal-article/",
+            Self::Chapter => "https://www.scribbr.com/apa-examples/edited-book-chapter/",
+            Self::Book => "https://www.scribbr.com/apa-examples/book/",
+            Self::Preprint => "https://apastyle.apa.org/style-grammar-guidelines/references/examples/preprint-article-references",
             Self::None => "",
         }
     }
+    // The fields of the format, in the order they are edited.
+    pub fn fields(&self) -> &'static [FieldDef] {
+        match self {
+            Self::Website => &schema::WEBSITE,
+            Self::Newspaper => &schema::NEWSPAPER,
+            Self::Dictionary => &schema::DICTIONARY,
+            Self::Journal => &schema::JOURNAL,
+            Self::Chapter => &schema::CHAPTER,
+            Self::Book => &schema::BOOK,
+            Self::Preprint => &schema::PREPRINT,
+            Self::None => &[],
+        }
+    }
+    // The field with the name of what it's published in, the book of a chapter...
+    pub fn container(&self) -> Option<&'static str> {
+        match self {
+            Self::Website => Some("website"),
+            Self::Newspaper => Some("newspaper"),
+            Self::Dictionary => Some("dictionary"),
+            Self::Journal => Some("journal"),
+            Self::Chapter => Some("book"),
+            Self::Preprint => Some("archive"),
+            Self::Book | Self::None => None,
+        }
+    }
 }
 
 impl fmt::Display for ApaFormatType {
@@ -37,19 +120,20 @@ impl fmt::Display for ApaFormatType {
             Self::Website => write!(f, "webpage"),
             Self::Newspaper => write!(f, "newspaper article"),
             Self::Dictionary => write!(f, "dictionary entry"),
+            Self::Journal => write!(f, "journal article"),
+            Self::Chapter => write!(f, "book chapter"),
+            Self::Book => write!(f, "book"),
+            Self::Preprint => write!(f, "preprint"),
             Self::None => write!(f, "none"),
         }
     }
 }
 
 pub struct ApaFormat {
-    // An apa format contains its type and a hashmap with fields representing
-    // its parts.
+    // An apa format contains its type and the contents of each of its fields,
+    // in the same order as the fields of its schema.
     pub format: ApaFormatType,
-    pub data: HashMap<usize, (String, String)>,
-
-    //Placeholders to print when the user hasn't added anything.
-    pub placeholders: HashMap<usize, (String, String)>,
+    pub data: Vec<String>,
 
     // Language of the APA format (used for date).
     pub lang: Lang,
@@ -57,103 +141,251 @@ pub struct ApaFormat {
 impl ApaFormat {
     pub fn new(format: ApaFormatType, lang: Option<Lang>) -> ApaFormat {
         // Creates an empty version of the apa format.
-        let mut data: HashMap<usize, (String, String)> = HashMap::new();
-        let mut placeholders: HashMap<usize, (String, String)> = HashMap::new();
-
-        // Online citations can benefit from mentioning the date in which
-        // they were consulted.
+        let data = vec![String::new(); format.fields().len()];
 
-        match format {
-            // Each format has a different amount and types of fields.
-            ApaFormatType::Website => {
-                const fields: &'static [&'static str] = &["authors","date", "title", "website", "URL"];
-                const field_placeholders: &'static [&'static str] = &["Author's Last Name, Initial(s)","date", "Title the article", "Website", "URL"];
-                data.fill_with_fields(fields, None);
-                placeholders.fill_with_fields(fields, Some(field_placeholders));
-            }
-            ApaFormatType::Newspaper => {
-                const fields: &'static [&'static str] = &["authors","date", "title", "newspaper", "URL"];
-                const field_placeholders: &'static [&'static str] = &["Author's Last Name, Initial(s)","date", "Title the article", "Newspaper", "URL"];
-                data.fill_with_fields(fields, None);
-                placeholders.fill_with_fields(fields, Some(field_placeholders));
-            }
-            ApaFormatType::Dictionary => {
-                const fields: &'static [&'static str] = &["authors", "date", "word", "editors", "dictionary", "publisher", "URL"];
-                const field_placeholders: &'static [&'static str] = &["Author's Last Name, Initial(s)","date", "Word", "Editors's Initial(s). Last Name", "Dictionary", "Publisher", "URL"];
-                data.fill_with_fields(fields, None);
-                placeholders.fill_with_fields(fields, Some(field_placeholders));
-            }
-            ApaFormatType::None => {}
-        };
         let lang = match lang {
             Some(lang) => lang,
             None => Lang::English
         };
 
-        ApaFormat { format, data, placeholders, lang}
+        ApaFormat { format, data, lang}
+    }
+
+    pub fn fields(&self) -> &'static [FieldDef] {
+        self.format.fields()
+    }
+
+    // Position of a field in the schema.
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.fields().iter().position(|field| field.id == id)
+    }
+
+    // What the user has written in the field, empty if the format doesn't have it.
+    pub fn get(&self, id: &str) -> &str {
+        match self.index_of(id) {
+            Some(i) => &self.data[i],
+            None => "",
+        }
+    }
+
+    // Set a field, returns false if the format doesn't have it.
+    pub fn set(&mut self, id: &str, value: impl Into<String>) -> bool {
+        match self.index_of(id) {
+            Some(i) => {
+                self.data[i] = value.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Get the contents of a field, or its placeholder if the user hasn't added anything.
+    pub fn field(&self, id: &str) -> String {
+        match self.index_of(id) {
+            Some(i) if !self.data[i].is_empty() => self.data[i].clone(),
+            Some(i) => self.fields()[i].placeholder.to_string(),
+            None => String::new(),
+        }
     }
-}
-// Fit everything into the format.
-impl fmt::Display for ApaFormat {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
-        // Get current date
-        let date = retrive_current_date(&self.lang);
+    // Journals, books and preprints only go by the year, the rest by the full date.
+    pub fn set_date(&mut self, date: &PublicationDate) -> bool {
+        let date = match self.format {
+            ApaFormatType::Journal | ApaFormatType::Chapter | ApaFormatType::Book | ApaFormatType::Preprint => date.year.to_string(),
+            _ => date.apa(&self.lang),
+        };
+        self.set("date", date)
+    }
 
+    // Warnings for each field, in schema order.
+    pub fn validate(&self) -> Vec<Option<String>> {
+        // A DOI takes the place of the URL.
+        let has_doi = self.doi().is_some();
+        self.fields().iter()
+            .zip(&self.data)
+            .map(|(field, value)| {
+                if field.id == "url" && has_doi && value.is_empty() {
+                    return None;
+                }
+                field.validate(value)
+            })
+            .collect()
+    }
+
+    // Build the reference with its italics and links.
+    pub fn reference(&self) -> RichText {
+        let mut reference = RichText::new();
+
+        // Defines how each apa format is structured.
         match self.format {
             ApaFormatType::None => {
-                write!(f, "none")
+                reference.plain("none");
             }
-            // Defines how each apa format is structured and 
             ApaFormatType::Website => {
-                // Here's the format.
-                let reference = format!("authors. (date). <i>title</i>. website. {} URL", date);
-
-                // We replace the field's names in the string of the data.
-                // We then add the placeholders if it wasn't modified.
-                let reference = replace_string_contents(reference, &self.data, &self.placeholders);
-
-                write!(f, "{}", reference)
-            },
+                reference
+                    .plain(self.author_date())
+                    .italic(self.field("title"))
+                    .plain(format!(". {}.", self.field("website")));
+                self.source(&mut reference, true);
+            }
             ApaFormatType::Newspaper => {
-                // Here's the format.
-                let reference = format!("authors. (date). <i>title</i>. newspaper. {} URL", date);
-                // We replace the field's names in the string of the data.
-                // We then add the placeholders if it wasn't modified.
-                let reference = replace_string_contents(reference, &self.data, &self.placeholders);
-
-                write!(f, "{}", reference)
+                reference
+                    .plain(self.author_date())
+                    .italic(self.field("title"))
+                    .plain(format!(". {}.", self.field("newspaper")));
+                self.source(&mut reference, true);
             }
             ApaFormatType::Dictionary => {
-                // Here's the format.
-                let reference = format!("authors. (date). word. In editors (Ed.). <i>dictionary</i>. publisher. {} URL", date);
-                // We replace the field's names in the string of the data.
-                // We then add the placeholders if it wasn't modified.
-                let reference = replace_string_contents(reference, &self.data, &self.placeholders);
-
-                write!(f, "{}", reference)
+                reference.plain(format!("{}{}. In ", self.author_date(), self.field("word")));
+                if !self.get("editors").is_empty() {
+                    reference.plain(format!("{}, ", self.editors()));
+                }
+                reference.italic(self.field("dictionary")).plain(".");
+                if !self.get("publisher").is_empty() {
+                    reference.plain(format!(" {}.", self.get("publisher")));
+                }
+                self.source(&mut reference, true);
+            }
+            // Journals and books don't change, so there is no retrieval date.
+            ApaFormatType::Journal => {
+                reference
+                    .plain(format!("{}{}. ", self.author_date(), self.field("title")))
+                    .italic(self.field("journal"));
+                if !self.get("volume").is_empty() {
+                    reference.plain(", ").italic(self.get("volume"));
+                }
+                if !self.get("issue").is_empty() {
+                    reference.plain(format!("({})", self.get("issue")));
+                }
+                if let Some(pages) = self.pages() {
+                    reference.plain(format!(", {}", pages));
+                }
+                reference.plain(".");
+                self.source(&mut reference, false);
+            }
+            ApaFormatType::Chapter => {
+                reference.plain(format!("{}{}. In ", self.author_date(), self.field("title")));
+                if !self.get("editors").is_empty() {
+                    reference.plain(format!("{}, ", self.editors()));
+                }
+                reference.italic(self.field("book"));
+                if let Some(pages) = self.pages() {
+                    reference.plain(format!(" ({})", pages));
+                }
+                reference.plain(format!(". {}.", self.field("publisher")));
+                self.source(&mut reference, false);
+            }
+            ApaFormatType::Book => {
+                reference.plain(self.author_date())
+                    .italic(self.field("title"));
+                if let Some(edition) = self.edition() {
+                    reference.plain(format!(" ({})", edition));
+                }
+                reference.plain(format!(". {}.", self.field("publisher")));
+                self.source(&mut reference, false);
+            }
+            // Not peer reviewed yet, the archive takes the place of the journal.
+            ApaFormatType::Preprint => {
+                let description = match self.lang {
+                    Lang::English => "Preprint",
+                    Lang::Spanish => "Prepublicación",
+                };
+                reference.plain(self.author_date())
+                    .italic(self.field("title"))
+                    .plain(format!(" [{}]. {}.", description, self.field("archive")));
+                self.source(&mut reference, false);
             }
         }
+        reference
     }
-}
 
-// Replace the contents of a string using a Hashmap.
-pub fn replace_string_contents(string: String, data: &HashMap<usize, (String, String)>, placeholders: &HashMap<usize, (String, String)>) -> String {
-    let mut output = string;
+    // "Last, F. (2020). ", the initials already end the authors with a period.
+    fn author_date(&self) -> String {
+        let authors = self.field("authors");
+        let period = if authors.ends_with('.') { "" } else { "." };
+        format!("{}{} ({}). ", authors, period, self.field("date"))
+    }
+
+    // "A. Editor (Ed.)", or "(Eds.)" when there are more.
+    fn editors(&self) -> String {
+        let editors = self.get("editors");
+        let marker = if parse_people(editors).len() > 1 { "Eds." } else { "Ed." };
+        format!("{} ({})", editors, marker)
+    }
+
+    // The DOI, or the URL with the date it was consulted if the content may change.
+    fn source(&self, reference: &mut RichText, retrieved: bool) {
+        if let Some(doi) = self.doi() {
+            reference.plain(" ").link(doi.url());
+        } else if retrieved {
+            reference
+                .plain(format!(" {} ", retrieved_on(&self.retrieved(), &self.lang)))
+                .link(self.url());
+        } else if !self.get("url").is_empty() {
+            reference.plain(" ").link(self.url());
+        }
+    }
 
-    for (i, field) in data {
-        // Check that the field is valid.
-        if field.1 != "" {
-            // Find and replace the field names to the field contents.
-            output = output.replace(&field.0, &field.1);
-        } else {
-            // Insert placeholder if field data is empty.
-            output = output.replace(&placeholders[i].0, &placeholders[i].1);
+    // The URL without tracking parameters, as typed (or the placeholder) if it isn't valid.
+    pub fn url(&self) -> String {
+        match Url::parse(self.get("url")) {
+            Ok(url) => url.to_string(),
+            Err(_) => self.field("url"),
         }
     }
 
-    return output;
+    // The day the page was consulted, today unless the user says otherwise.
+    pub fn retrieved(&self) -> PublicationDate {
+        PublicationDate::parse(self.get("retrieved")).unwrap_or_else(today)
+    }
 
+    // The DOI of the reference, None if there isn't one or it isn't valid.
+    pub fn doi(&self) -> Option<Doi> {
+        Doi::parse(self.get("doi")).ok()
+    }
+
+    // "2nd ed." from "2", "2nd" or "2nd ed.", None for first editions that aren't written.
+    pub fn edition(&self) -> Option<String> {
+        let edition = self.get("edition").trim().trim_end_matches('.');
+        let lowercase = edition.to_lowercase();
+        let edition = [" edition", " edición", " ed"].iter()
+            .find(|suffix| lowercase.ends_with(*suffix))
+            .map(|suffix| &edition[..edition.len() - suffix.len()])
+            .unwrap_or(edition)
+            .trim();
+        if edition.is_empty() {
+            return None;
+        }
+        let number = edition.trim_end_matches(|c: char| c.is_alphabetic() || c == '.' || c == 'ª').parse::<u32>();
+        Some(match (number, self.lang) {
+            (Ok(number), Lang::Spanish) => format!("{}.ª ed.", number),
+            (Ok(number), Lang::English) => {
+                let suffix = match (number % 10, number % 100) {
+                    (_, 11..=13) => "th",
+                    (1, _) => "st",
+                    (2, _) => "nd",
+                    (3, _) => "rd",
+                    _ => "th",
+                };
+                format!("{}{} ed.", number, suffix)
+            }
+            // "Revised", "Special"...
+            (Err(_), _) => format!("{} ed.", edition),
+        })
+    }
+
+    // The pages in their APA form, None if the user hasn't added them.
+    pub fn pages(&self) -> Option<String> {
+        PageLocator::parse(self.get("pages"))
+            .map(|(locator, _)| locator.format(self.format))
+    }
+}
+
+// Plain text version of the reference, use reference() to keep the italics.
+impl fmt::Display for ApaFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.reference().render(&rich::Plain::default()))
+    }
 }
 
 // Base logic of the program
@@ -166,23 +398,97 @@ pub struct Logic {
     pub cursor_pos: usize,
 
     pub apa: ApaFormat,
+
+    // Shown to the user, like where a file was saved.
+    pub message: String,
+
+    /* Importing */
+    // What the user typed in the prompt, like the path of a file.
+    pub prompt: String,
+    // The references found, for the picker.
+    pub candidates: Vec<Candidate>,
+    // Fields the import only guessed, the editor marks them.
+    pub review: Vec<&'static str>,
 }
 
 #[derive(PartialEq, Eq)]
 pub enum LogicState {
     /* APA format selector */
     SelectingFormat,
+    /* Asking for a path, an identifier... */
+    Prompt(PromptKind),
+    /* Choosing one of the references found */
+    Picking,
     /* APA editor */
     EditState,
     /* APA copying state (process must be alive to save to clipboard.) */
     Result
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Lang {
     English,
     Spanish,
 }
+impl Lang {
+    // A list of references is in the language of its first one.
+    pub fn of(references: &[ApaFormat]) -> Lang {
+        references.first().map_or(Lang::English, |apa| apa.lang)
+    }
+
+    // The title of the reference list in documents.
+    pub fn references_heading(&self) -> &'static str {
+        match self {
+            Lang::English => "References",
+            Lang::Spanish => "Referencias",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    OpenFile,
+    Doi,
+    Isbn,
+    Url,
+    Pubmed,
+    Preprint,
+    Citation,
+    Zotero,
+}
+impl PromptKind {
+    // What the header says.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::OpenFile => "open a .bib, .ris or CSL .json file",
+            Self::Doi => "fetch a reference by DOI",
+            Self::Isbn => "fetch a book by ISBN",
+            Self::Url => "fetch a web page or open a saved .html file",
+            Self::Pubmed => "fetch an article from PubMed by PMID or PMCID",
+            Self::Preprint => "fetch a preprint by arXiv ID or DOI",
+            Self::Citation => "paste a formatted APA or MLA reference",
+            Self::Zotero => "search your Zotero library",
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OpenFile => "File",
+            Self::Doi => "DOI",
+            Self::Isbn => "ISBN",
+            Self::Url => "URL or file",
+            Self::Pubmed => "PMID or PMCID",
+            Self::Preprint => "arXiv ID or DOI",
+            Self::Citation => "Reference",
+            Self::Zotero => "Search",
+        }
+    }
+}
+
+impl Default for Logic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Logic {
     pub fn new() -> Logic {
@@ -192,48 +498,333 @@ impl Logic {
             selected: 0,
             cursor_pos: 0,
             apa: ApaFormat::new(ApaFormatType::None, None),
+            message: String::new(),
+            prompt: String::new(),
+            candidates: Vec::new(),
+            review: Vec::new(),
+        }
+    }
+
+    // Edit a reference that was imported, telling the user what didn't fit.
+    pub fn load(&mut self, candidate: Candidate) {
+        self.apa = candidate.apa;
+        self.message = candidate.warnings.join("; ");
+        self.review = candidate.review;
+        self.candidates.clear();
+        self.state = LogicState::EditState;
+        self.edit_state = false;
+        self.selected = 0;
+        self.cursor_pos = 0;
+    }
+
+    // The selected field was changed by the user, it isn't a guess anymore.
+    pub fn reviewed(&mut self) {
+        if let Some(field) = self.apa.fields().get(self.selected) {
+            self.review.retain(|id| *id != field.id);
+        }
+    }
+
+    // Keys of the prompt and the picker, true when the screen changes and has to be cleared.
+    // Each key is handled by a single screen, Enter in the prompt mustn't also pick a reference.
+    pub fn import_key(&mut self, key: &Key, lookup: impl FnOnce(PromptKind, &str) -> Result<Vec<Candidate>, String>) -> bool {
+        match (&self.state, key) {
+            (LogicState::Prompt(kind), Key::Char('\n')) => {
+                match lookup(*kind, &self.prompt) {
+                    // Nothing to choose from with a single reference.
+                    Ok(mut candidates) if candidates.len() == 1 => self.load(candidates.remove(0)),
+                    Ok(candidates) => {
+                        self.candidates = candidates;
+                        self.selected = 0;
+                        self.message = String::new();
+                        self.state = LogicState::Picking;
+                    }
+                    Err(error) => self.message = error,
+                }
+                true
+            }
+            (LogicState::Prompt(_), Key::Char(c)) => {
+                self.prompt.push(*c);
+                false
+            }
+            (LogicState::Prompt(_), Key::Backspace) => {
+                self.prompt.pop();
+                false
+            }
+            (LogicState::Prompt(_), Key::Esc) => {
+                self.state = LogicState::SelectingFormat;
+                self.message = String::new();
+                true
+            }
+            (LogicState::Picking, Key::Up) if self.selected != 0 => {
+                self.selected -= 1;
+                false
+            }
+            (LogicState::Picking, Key::Down) if self.selected + 1 < self.candidates.len() => {
+                self.selected += 1;
+                false
+            }
+            (LogicState::Picking, Key::Char('\n')) => {
+                let candidate = self.candidates.remove(self.selected);
+                self.load(candidate);
+                true
+            }
+            (LogicState::Picking, Key::Esc) => {
+                self.candidates.clear();
+                self.selected = 0;
+                self.state = LogicState::SelectingFormat;
+                true
+            }
+            _ => false,
         }
     }
 }
 
-pub fn save_to_x11_clipboard(clipboard: &Clipboard, format_apa: &ApaFormat) {
+pub fn save_to_x11_clipboard(clipboard: &Clipboard, format_apa: &ApaFormat, output: OutputFormat) {
     // Create clipboard
     //TODO: MAYBE IT DOESN'T WORK BECAUSE THE CLIPBOARD GOES OUT OF SCOPE INSTANTLY ANYWAYS:
     // FIX BY CREATING THE CLIPBOARD IN MAIN.
 
+    let content = match output {
+        OutputFormat::Html => html::clipboard(format_apa),
+        _ => output.render(format_apa),
+    };
+
     clipboard.store(
         //Where?
         clipboard.getter.atoms.clipboard, 
         // Determine format.
-        clipboard.getter.get_atom("text/html").unwrap(), 
-        format!("<meta http-equiv=\"content-type\" content=\"text; charset=utf-8\">{}", format_apa),
+        clipboard.getter.get_atom(output.mime_type()).unwrap(), 
+        content,
     ).unwrap();
 
     //thread::sleep(Duration::from_millis(10000));
 }
 
-pub trait ApaFiller {
-    /// Fills the HashMap with fields and values.
-    fn fill_with_fields(&mut self, fields: &[&str], content: Option<&[&str]>);
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl ApaFiller for HashMap<usize, (String, String)> {
-    fn fill_with_fields(&mut self, fields: &[&str], content: Option<&[&str]>) {
-        
-        // Check if there should be any content
-        match content {
-            Some(content) => {
-                // Fill the hashmap with custom content.
-                for (i, data) in fields.iter().zip(content).enumerate() {
-                    self.insert(i, (data.0.to_string(), data.1.to_string()));
-                }
-            }
-            None => {
-                // Fill the fields with nothing in their content.
-                for (i, field) in fields.iter().enumerate() {
-                    self.insert(i, (field.to_string(), "".to_string()));
-                }
-            }
+    fn candidates(titles: &[&str]) -> Vec<Candidate> {
+        titles.iter()
+            .map(|title| {
+                let mut apa = ApaFormat::new(ApaFormatType::Journal, None);
+                apa.set("title", *title);
+                Candidate::new("", apa, Vec::new())
+            })
+            .collect()
+    }
+
+    fn prompt(logic: &mut Logic, text: &str) {
+        logic.state = LogicState::Prompt(PromptKind::OpenFile);
+        for c in text.chars() {
+            logic.import_key(&Key::Char(c), |_, _| unreachable!());
         }
     }
-}
\ No newline at end of file
+
+    fn reference(format: ApaFormatType, fields: &[(&str, &str)]) -> String {
+        let mut apa = ApaFormat::new(format, Some(Lang::English));
+        for (id, value) in fields {
+            assert!(apa.set(id, *value), "{} has no {}", format, id);
+        }
+        apa.reference().render(&rich::Plain::new(rich::Italics::Underscores))
+    }
+
+    #[test]
+    fn website_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Website, &[
+                ("authors", "Smith, J. A., & Pérez, M."),
+                ("date", "2020, May 4"),
+                ("title", "Reading rooms"),
+                ("website", "City Library"),
+                ("url", "https://example.org/rooms"),
+                ("retrieved", "2021-06-01"),
+            ]),
+            "Smith, J. A., & Pérez, M. (2020, May 4). _Reading rooms_. City Library. Retrived June 1, 2021, from https://example.org/rooms",
+        );
+    }
+
+    #[test]
+    fn organizations_get_a_period() {
+        assert_eq!(
+            reference(ApaFormatType::Newspaper, &[
+                ("authors", "World Health Organization"),
+                ("date", "2020, March 11"),
+                ("title", "A pandemic is declared"),
+                ("newspaper", "The Daily"),
+                ("doi", "10.1000/xyz"),
+            ]),
+            "World Health Organization. (2020, March 11). _A pandemic is declared_. The Daily. https://doi.org/10.1000/xyz",
+        );
+    }
+
+    #[test]
+    fn dictionary_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Dictionary, &[
+                ("authors", "Merriam-Webster"),
+                ("date", "n.d."),
+                ("word", "Reference"),
+                ("editors", "A. Editor"),
+                ("dictionary", "Merriam-Webster.com dictionary"),
+                ("publisher", "Merriam-Webster"),
+                ("url", "https://www.merriam-webster.com/dictionary/reference"),
+                ("retrieved", "2021-06-01"),
+            ]),
+            "Merriam-Webster. (n.d.). Reference. In A. Editor (Ed.), _Merriam-Webster.com dictionary_. Merriam-Webster. Retrived June 1, 2021, from https://www.merriam-webster.com/dictionary/reference",
+        );
+    }
+
+    #[test]
+    fn dictionaries_without_editors_or_publisher() {
+        assert_eq!(
+            reference(ApaFormatType::Dictionary, &[
+                ("authors", "Merriam-Webster"),
+                ("date", "n.d."),
+                ("word", "Reference"),
+                ("dictionary", "Merriam-Webster.com dictionary"),
+                ("url", "https://www.merriam-webster.com/dictionary/reference"),
+                ("retrieved", "2021-06-01"),
+            ]),
+            "Merriam-Webster. (n.d.). Reference. In _Merriam-Webster.com dictionary_. Retrived June 1, 2021, from https://www.merriam-webster.com/dictionary/reference",
+        );
+    }
+
+    #[test]
+    fn journal_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Journal, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A study of things"),
+                ("journal", "Journal of Things"),
+                ("volume", "12"),
+                ("issue", "3"),
+                ("pages", "45-67"),
+                ("doi", "10.1000/xyz"),
+            ]),
+            "Smith, J. A. (2020). A study of things. _Journal of Things_, _12_(3), 45–67. https://doi.org/10.1000/xyz",
+        );
+    }
+
+    #[test]
+    fn chapter_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Chapter, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A chapter"),
+                ("editors", "A. Editor"),
+                ("book", "A book"),
+                ("pages", "45-67"),
+                ("publisher", "Press"),
+            ]),
+            "Smith, J. A. (2020). A chapter. In A. Editor (Ed.), _A book_ (pp. 45–67). Press.",
+        );
+    }
+
+    #[test]
+    fn chapters_with_more_editors() {
+        assert_eq!(
+            reference(ApaFormatType::Chapter, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A chapter"),
+                ("editors", "A. Editor & B. Editor"),
+                ("book", "A book"),
+                ("publisher", "Press"),
+            ]),
+            "Smith, J. A. (2020). A chapter. In A. Editor & B. Editor (Eds.), _A book_. Press.",
+        );
+    }
+
+    #[test]
+    fn book_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Book, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A book"),
+                ("edition", "2"),
+                ("publisher", "Press"),
+                ("url", "https://example.org/book"),
+            ]),
+            "Smith, J. A. (2020). _A book_ (2nd ed.). Press. https://example.org/book",
+        );
+    }
+
+    #[test]
+    fn preprint_reference() {
+        assert_eq!(
+            reference(ApaFormatType::Preprint, &[
+                ("authors", "Smith, J. A."),
+                ("date", "2020"),
+                ("title", "A preprint"),
+                ("archive", "arXiv"),
+                ("url", "https://arxiv.org/abs/2001.00001v2"),
+            ]),
+            "Smith, J. A. (2020). _A preprint_ [Preprint]. arXiv. https://arxiv.org/abs/2001.00001v2",
+        );
+    }
+
+    #[test]
+    fn placeholders_fill_the_empty_fields() {
+        assert_eq!(reference(ApaFormatType::None, &[]), "none");
+        assert_eq!(
+            reference(ApaFormatType::Book, &[]),
+            "Author's Last Name, Initial(s). (year). _Title of the book_. Publisher.",
+        );
+    }
+
+    #[test]
+    fn enter_in_the_prompt_shows_the_picker() {
+        let mut logic = Logic::new();
+        prompt(&mut logic, "refs.bib");
+        let changed = logic.import_key(&Key::Char('\n'), |kind, input| {
+            assert_eq!((kind, input), (PromptKind::OpenFile, "refs.bib"));
+            Ok(candidates(&["First", "Second"]))
+        });
+        assert!(changed);
+        assert!(logic.state == LogicState::Picking);
+        assert_eq!(logic.candidates.len(), 2);
+
+        // Only the next Enter picks, and it picks the selected one.
+        logic.import_key(&Key::Down, |_, _| unreachable!());
+        logic.import_key(&Key::Char('\n'), |_, _| unreachable!());
+        assert!(logic.state == LogicState::EditState);
+        assert_eq!(logic.apa.get("title"), "Second");
+        assert!(logic.candidates.is_empty());
+    }
+
+    #[test]
+    fn a_single_reference_is_opened_directly() {
+        let mut logic = Logic::new();
+        prompt(&mut logic, "one.ris");
+        logic.import_key(&Key::Char('\n'), |_, _| Ok(candidates(&["Only"])));
+        assert!(logic.state == LogicState::EditState);
+        assert_eq!(logic.apa.get("title"), "Only");
+    }
+
+    #[test]
+    fn errors_stay_in_the_prompt() {
+        let mut logic = Logic::new();
+        prompt(&mut logic, "missing.bib");
+        logic.import_key(&Key::Char('\n'), |_, _| Err("Couldn't open missing.bib".to_string()));
+        assert!(logic.state == LogicState::Prompt(PromptKind::OpenFile));
+        assert_eq!(logic.message, "Couldn't open missing.bib");
+    }
+
+    #[test]
+    fn the_picker_stays_in_bounds() {
+        let mut logic = Logic::new();
+        prompt(&mut logic, "refs.bib");
+        logic.import_key(&Key::Char('\n'), |_, _| Ok(candidates(&["First", "Second"])));
+        for _ in 0..3 {
+            logic.import_key(&Key::Down, |_, _| unreachable!());
+        }
+        assert_eq!(logic.selected, 1);
+        logic.import_key(&Key::Esc, |_, _| unreachable!());
+        assert!(logic.state == LogicState::SelectingFormat);
+        assert!(logic.candidates.is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+// Books by ISBN from Open Library, or any server with the same /api/books.
+
+use serde_json::Value;
+
+use crate::date::PublicationDate;
+use crate::fetch::{join, FetchError, HttpClient};
+use crate::isbn::Isbn;
+use crate::person::{format_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// The book with that ISBN, with warnings about what's missing.
+pub fn lookup(client: &dyn HttpClient, base_url: &str, isbn: &Isbn, lang: Lang) -> Result<(ApaFormat, Vec<String>), FetchError> {
+    // Older books may only be listed under their ISBN-10.
+    let mut keys = vec![format!("ISBN:{}", isbn.isbn13())];
+    keys.extend(isbn.isbn10().map(|isbn10| format!("ISBN:{}", isbn10)));
+
+    let url = join(base_url, &format!("api/books?bibkeys={}&format=json&jscmd=details", keys.join(",")));
+    let body = client.get(&url, "application/json")?;
+    let value: Value = serde_json::from_str(&body).map_err(|error| FetchError::Invalid(error.to_string()))?;
+
+    keys.iter()
+        .find_map(|key| value.get(key)?.get("details"))
+        .map(|details| from_details(details, lang))
+        .ok_or(FetchError::NotFound)
+}
+
+// The edition record, "details" in the answer.
+pub fn from_details(details: &Value, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let mut apa = ApaFormat::new(ApaFormatType::Book, Some(lang));
+    let mut warnings = Vec::new();
+
+    let authors: Vec<Person> = details.get("authors")
+        .and_then(Value::as_array)
+        .map(|authors| {
+            authors.iter()
+                .filter_map(|author| author.get("name")?.as_str())
+                .map(Person::from_full_name)
+                .collect()
+        })
+        .unwrap_or_default();
+    if authors.is_empty() {
+        warnings.push("Open Library has no authors for this book".to_string());
+    }
+    apa.set("authors", format_people(&authors));
+
+    if let Some(date) = PublicationDate::parse(&text(details, "publish_date")) {
+        apa.set_date(&date);
+    }
+
+    let title = text(details, "title");
+    let subtitle = text(details, "subtitle");
+    apa.set("title", if subtitle.is_empty() { title } else { format!("{}: {}", title, subtitle) });
+    apa.set("edition", text(details, "edition_name"));
+    apa.set("publisher", text(details, "publishers"));
+
+    (apa, warnings)
+}
+
+// A string, or the first one of a list.
+fn text(value: &Value, key: &str) -> String {
+    match value.get(key) {
+        Some(Value::String(text)) => text.trim().to_string(),
+        Some(Value::Array(values)) => values.first().and_then(Value::as_str).unwrap_or("").trim().to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FakeClient;
+
+    const URL: &str = "https://openlibrary.org/api/books?bibkeys=ISBN:9780131103627,ISBN:0131103628&format=json&jscmd=details";
+
+    #[test]
+    fn books_listed_under_their_isbn10() {
+        let client = FakeClient::new(&[(URL, include_str!("../../tests/fixtures/openlibrary/details.json"))]);
+        let isbn = Isbn::parse("978-0-13-110362-7").unwrap();
+        let (apa, warnings) = lookup(&client, "https://openlibrary.org/", &isbn, Lang::English).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(apa.get("authors"), "Kernighan, B. W., & Ritchie, D. M.");
+        assert_eq!(apa.get("date"), "1988");
+        assert_eq!(apa.get("title"), "The C programming language: ANSI C");
+        assert_eq!(apa.get("edition"), "2nd ed.");
+        assert_eq!(apa.get("publisher"), "Prentice Hall");
+    }
+
+    #[test]
+    fn unknown_books_are_not_found() {
+        // Open Library answers an empty object.
+        let client = FakeClient::new(&[(URL, "{}")]);
+        let isbn = Isbn::parse("0131103628").unwrap();
+        assert_eq!(lookup(&client, "https://openlibrary.org", &isbn, Lang::English).err(), Some(FetchError::NotFound));
+    }
+
+    #[test]
+    fn books_without_authors_warn() {
+        let (apa, warnings) = from_details(&serde_json::json!({ "title": "Anonymous", "publish_date": "May 2001" }), Lang::English);
+        assert_eq!(apa.get("date"), "2001");
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+// The formats a finished reference can be copied or saved as.
+
+use std::{fmt, fs, io, path::Path, slice};
+
+use crate::ApaFormat;
+use crate::rich::{self, Italics};
+use crate::bibtex::{self, BibtexOptions};
+use crate::{biblatex, csl, document, hayagriva, html, markdown, ris, rtf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Html,
+    PlainText,
+    UnicodeItalics,
+    UnderscoreItalics,
+    Rtf,
+    BibLatex,
+    BibTex,
+    /// BibTeX with the accents written as TeX commands.
+    BibTexAscii,
+    Ris,
+    CslJson,
+    Markdown,
+    Docx,
+    Odt,
+    Hayagriva,
+}
+
+impl OutputFormat {
+    // Used for the generation of the output list in the result screen.
+    pub fn list() -> [OutputFormat; 14] {
+        [
+            OutputFormat::Html, OutputFormat::PlainText, OutputFormat::UnicodeItalics, OutputFormat::UnderscoreItalics,
+            OutputFormat::Rtf, OutputFormat::BibLatex, OutputFormat::BibTex, OutputFormat::BibTexAscii, OutputFormat::Ris,
+            OutputFormat::CslJson, OutputFormat::Markdown, OutputFormat::Docx, OutputFormat::Odt,
+            OutputFormat::Hayagriva,
+        ]
+    }
+
+    // Name used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::PlainText => "plain",
+            Self::UnicodeItalics => "unicode",
+            Self::UnderscoreItalics => "underscores",
+            Self::Rtf => "rtf",
+            Self::BibLatex => "biblatex",
+            Self::BibTex => "bibtex",
+            Self::BibTexAscii => "bibtex-ascii",
+            Self::Ris => "ris",
+            Self::CslJson => "csl-json",
+            Self::Markdown => "markdown",
+            Self::Docx => "docx",
+            Self::Odt => "odt",
+            Self::Hayagriva => "hayagriva",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        Self::list().into_iter().find(|output| output.name() == name)
+    }
+
+    // Documents can only be saved, not copied.
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::Docx | Self::Odt)
+    }
+
+    // What the clipboard is told the content is.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Html => "text/html",
+            Self::PlainText | Self::UnicodeItalics | Self::UnderscoreItalics | Self::Markdown => "UTF8_STRING",
+            Self::Docx | Self::Odt => "UTF8_STRING",
+            Self::BibLatex | Self::BibTex | Self::BibTexAscii | Self::Ris | Self::CslJson | Self::Hayagriva => "UTF8_STRING",
+            Self::Rtf => "text/rtf",
+        }
+    }
+
+    // Extension of the files saved in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::PlainText | Self::UnicodeItalics | Self::UnderscoreItalics => "txt",
+            Self::Rtf => "rtf",
+            Self::BibLatex | Self::BibTex | Self::BibTexAscii => "bib",
+            Self::Ris => "ris",
+            Self::CslJson => "json",
+            Self::Markdown => "md",
+            Self::Docx => "docx",
+            Self::Odt => "odt",
+            Self::Hayagriva => "yml",
+        }
+    }
+
+    pub fn render(&self, apa: &ApaFormat) -> String {
+        match self {
+            // Just the reference, to paste it into an existing list.
+            Self::Markdown => markdown::reference(apa),
+            Self::Html => html::reference(apa),
+            _ => self.render_list(slice::from_ref(apa)),
+        }
+    }
+
+    // Many references at once, as a reference list. Documents give their text
+    // only, use save() to get the real thing.
+    pub fn render_list(&self, references: &[ApaFormat]) -> String {
+        let backend: Box<dyn rich::Backend> = match self {
+            Self::Html => return html::document(references),
+            Self::PlainText | Self::Docx | Self::Odt => Box::new(rich::Plain::new(Italics::None)),
+            Self::UnicodeItalics => Box::new(rich::Plain::new(Italics::Unicode)),
+            Self::UnderscoreItalics => Box::new(rich::Plain::new(Italics::Underscores)),
+            Self::Rtf => return rtf::document(references),
+            Self::BibLatex => return biblatex::bibliography(references),
+            Self::BibTex => return bibtex::bibliography(references, BibtexOptions { tex_accents: false }),
+            Self::BibTexAscii => return bibtex::bibliography(references, BibtexOptions { tex_accents: true }),
+            Self::Ris => return ris::file(references),
+            Self::CslJson => return csl::items(references),
+            Self::Markdown => return markdown::references_section(references),
+            Self::Hayagriva => return hayagriva::file(references),
+        };
+        in_reference_order(references).iter()
+            .map(|apa| apa.reference().render(backend.as_ref()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn save(&self, path: &Path, references: &[ApaFormat]) -> io::Result<()> {
+        match self {
+            Self::Rtf => rtf::write_file(path, references),
+            Self::Html => html::write_file(path, references),
+            Self::Docx => document::write_docx(path, references),
+            Self::Odt => document::write_odt(path, references),
+            _ => fs::write(path, self.render_list(references)),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Html => write!(f, "html"),
+            Self::PlainText => write!(f, "plain text"),
+            Self::UnicodeItalics => write!(f, "unicode italics"),
+            Self::UnderscoreItalics => write!(f, "_underscores_"),
+            Self::Rtf => write!(f, "rtf"),
+            Self::BibLatex => write!(f, "biblatex"),
+            Self::BibTex => write!(f, "bibtex"),
+            Self::BibTexAscii => write!(f, "bibtex (ascii)"),
+            Self::Ris => write!(f, "ris"),
+            Self::CslJson => write!(f, "csl-json"),
+            Self::Markdown => write!(f, "markdown"),
+            Self::Docx => write!(f, "docx"),
+            Self::Odt => write!(f, "odt"),
+            Self::Hayagriva => write!(f, "hayagriva"),
+        }
+    }
+}
+
+// APA sorts the reference list alphabetically, by the first author.
+pub fn in_reference_order(references: &[ApaFormat]) -> Vec<&ApaFormat> {
+    let mut sorted: Vec<(String, &ApaFormat)> = references.iter()
+        .map(|apa| (apa.to_string().to_lowercase(), apa))
+        .collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    sorted.into_iter().map(|(_, apa)| apa).collect()
+}
@@ -0,0 +1,155 @@
+// Authors and editors written in a single field, "Last, F. M., & Last, F." or "F. M. Last".
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Person {
+    pub family: String,
+    /// Initials or given names, empty for organizations.
+    pub given: String,
+}
+
+impl Person {
+    pub fn new(family: impl Into<String>, given: impl Into<String>) -> Person {
+        Person { family: family.into(), given: given.into() }
+    }
+
+    // "Jan van der Berg", a name written the way it's said. The last name
+    // starts at the particle ("van", "de"...) or is the last word.
+    pub fn from_full_name(name: &str) -> Person {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let start = (1..words.len())
+            .find(|i| words[*i].starts_with(char::is_lowercase))
+            .unwrap_or(words.len().saturating_sub(1));
+        Person::new(words[start..].join(" "), words[..start].join(" "))
+    }
+
+    // Organizations and groups only have one name.
+    pub fn is_organization(&self) -> bool {
+        self.given.is_empty()
+    }
+
+    // "J. R. R." from "John Ronald Reuel", initials stay as they are.
+    pub fn initials(&self) -> String {
+        self.given.split_whitespace()
+            .map(|name| {
+                if is_initials(name) {
+                    return name.to_string();
+                }
+                // Hyphenated names keep the hyphen, "Jean-Paul" is "J.-P.".
+                name.split('-')
+                    .filter_map(|part| part.chars().next())
+                    .map(|c| format!("{}.", c.to_uppercase()))
+                    .collect::<Vec<String>>()
+                    .join("-")
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    // "F. M. Last", how APA writes editors after "In".
+    pub fn initials_first(&self) -> String {
+        if self.is_organization() {
+            self.family.clone()
+        } else {
+            format!("{} {}", self.initials(), self.family)
+        }
+    }
+
+    // "Last, F. M.", how APA lists authors.
+    pub fn apa(&self) -> String {
+        if self.is_organization() {
+            self.family.clone()
+        } else {
+            format!("{}, {}", self.family, self.initials())
+        }
+    }
+}
+
+pub fn parse_people(text: &str) -> Vec<Person> {
+    // "&", "and" and the spanish "y" are just one more separator.
+    let text = strip_editor_marker(text).replace(" & ", ", ").replace(" and ", ", ").replace(" y ", ", ").replace(';', ",");
+    let parts = text.split(',')
+        .map(|part| part.trim().trim_start_matches('&').trim())
+        .filter(|part| !part.is_empty());
+
+    let mut people: Vec<Person> = Vec::new();
+    for part in parts {
+        // "Last, F." comes in two parts, the initials go with the name before them.
+        // Full given names ("Last, First") only after a one word last name.
+        if let Some(person) = people.last_mut() {
+            let single_family = !person.family.contains(' ');
+            if person.given.is_empty() && (is_initials(part) || (single_family && is_given_names(part))) {
+                person.given = part.to_string();
+                continue;
+            }
+        }
+
+        // "F. M. Last", editors are written the other way around.
+        let words: Vec<&str> = part.split_whitespace().collect();
+        let leading_initials = words.iter().take_while(|word| is_initials(word)).count();
+        if leading_initials > 0 && leading_initials < words.len() {
+            people.push(Person::new(words[leading_initials..].join(" "), words[..leading_initials].join(" ")));
+        } else {
+            people.push(Person::new(part, ""));
+        }
+    }
+    people
+}
+
+fn is_given_names(text: &str) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    words.len() <= 3
+        && words.iter().all(|word| {
+            word.chars().next().is_some_and(char::is_uppercase) && !word.chars().any(|c| c.is_ascii_digit())
+        })
+}
+
+// "F.", "F. M.", "J.-P."
+pub fn is_initials(text: &str) -> bool {
+    !text.is_empty()
+        && text.split_whitespace().all(|word| {
+            word.ends_with('.')
+                && word.split('-').all(|part| {
+                    let letters = part.trim_end_matches('.');
+                    !letters.is_empty() && letters.chars().count() <= 2 && letters.chars().next().is_some_and(char::is_uppercase)
+                })
+        })
+}
+
+// Back to a single field, "Last, F., Last, F., & Last, F.".
+pub fn format_people(people: &[Person]) -> String {
+    let names: Vec<String> = people.iter().map(Person::apa).collect();
+    match names.as_slice() {
+        [] => String::new(),
+        [one] => one.clone(),
+        [rest @ .., last] => format!("{}, & {}", rest.join(", "), last),
+    }
+}
+
+// Edited books go by their editors, "Last, F. (Ed.)" in the authors field.
+pub fn format_book_editors(people: &[Person]) -> String {
+    match people.len() {
+        0 => String::new(),
+        1 => format!("{} (Ed.)", format_people(people)),
+        _ => format!("{} (Eds.)", format_people(people)),
+    }
+}
+
+// The authors without the "(Ed.)" or "(Eds.)" after them.
+pub fn strip_editor_marker(text: &str) -> &str {
+    let text = text.trim_end();
+    text.strip_suffix("(Eds.)")
+        .or_else(|| text.strip_suffix("(Ed.)"))
+        .map(str::trim_end)
+        .unwrap_or(text)
+}
+
+// "A. Editor & B. Editor", or with commas when there are more than two.
+pub fn format_editors(people: &[Person]) -> String {
+    let names: Vec<String> = people.iter().map(Person::initials_first).collect();
+    match names.as_slice() {
+        [] => String::new(),
+        [one] => one.clone(),
+        [first, second] => format!("{} & {}", first, second),
+        [rest @ .., last] => format!("{}, & {}", rest.join(", "), last),
+    }
+}
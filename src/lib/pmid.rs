@@ -0,0 +1,130 @@
+// PubMed identifiers: "PMID: 12345678", "PMC1234567" or the article links of PubMed and PMC.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PubmedId {
+    /// A PubMed record, only the digits.
+    Pmid(String),
+    /// A PubMed Central article, the digits without "PMC".
+    Pmcid(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PubmedIdError {
+    Empty,
+    InvalidCharacter(char),
+    TooLong,
+}
+
+impl fmt::Display for PubmedIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "PMID is empty"),
+            Self::InvalidCharacter(c) => write!(f, "PMIDs and PMCIDs are numbers, they can't contain \"{}\"", c),
+            Self::TooLong => write!(f, "PMIDs have at most 8 digits"),
+        }
+    }
+}
+
+impl PubmedId {
+    pub fn parse(input: &str) -> Result<PubmedId, PubmedIdError> {
+        let mut text = input.trim();
+        // The last part of a link, "pubmed.ncbi.nlm.nih.gov/12345678/".
+        if text.contains("://") {
+            text = text.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        }
+        let lower = text.to_lowercase();
+        let mut central = false;
+        for prefix in ["pmcid:", "pmid:", "pmc"] {
+            if lower.starts_with(prefix) {
+                text = text[prefix.len()..].trim_start();
+                central = prefix != "pmid:";
+                break;
+            }
+        }
+        // "PMCID: PMC1234567"
+        if central && text.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("pmc")) {
+            text = &text[3..];
+        }
+        let text = text.trim_end_matches('.');
+
+        if text.is_empty() {
+            return Err(PubmedIdError::Empty);
+        }
+        if let Some(c) = text.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(PubmedIdError::InvalidCharacter(c));
+        }
+        // Leading zeros aren't part of the id.
+        let digits = text.trim_start_matches('0').to_string();
+        if digits.is_empty() {
+            return Err(PubmedIdError::Empty);
+        }
+        if central {
+            Ok(PubmedId::Pmcid(digits))
+        } else if digits.len() > 8 {
+            Err(PubmedIdError::TooLong)
+        } else {
+            Ok(PubmedId::Pmid(digits))
+        }
+    }
+}
+
+impl fmt::Display for PubmedId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pmid(digits) => write!(f, "PMID {}", digits),
+            Self::Pmcid(digits) => write!(f, "PMC{}", digits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pmid(digits: &str) -> Result<PubmedId, PubmedIdError> {
+        Ok(PubmedId::Pmid(digits.to_string()))
+    }
+
+    fn pmcid(digits: &str) -> Result<PubmedId, PubmedIdError> {
+        Ok(PubmedId::Pmcid(digits.to_string()))
+    }
+
+    #[test]
+    fn pmids() {
+        assert_eq!(PubmedId::parse("32171076"), pmid("32171076"));
+        assert_eq!(PubmedId::parse("PMID: 32171076."), pmid("32171076"));
+        assert_eq!(PubmedId::parse("pmid:00123"), pmid("123"));
+    }
+
+    #[test]
+    fn pmcids() {
+        assert_eq!(PubmedId::parse("PMC7270627"), pmcid("7270627"));
+        assert_eq!(PubmedId::parse("pmc 7270627"), pmcid("7270627"));
+        assert_eq!(PubmedId::parse("PMCID: PMC7270627"), pmcid("7270627"));
+        assert_eq!(PubmedId::parse("PMCID: 7270627"), pmcid("7270627"));
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(PubmedId::parse("https://pubmed.ncbi.nlm.nih.gov/32171076/"), pmid("32171076"));
+        assert_eq!(PubmedId::parse("https://www.ncbi.nlm.nih.gov/pmc/articles/PMC7270627/"), pmcid("7270627"));
+        assert_eq!(PubmedId::parse("https://pmc.ncbi.nlm.nih.gov/articles/PMC7270627"), pmcid("7270627"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(PubmedId::parse(" "), Err(PubmedIdError::Empty));
+        assert_eq!(PubmedId::parse("PMC"), Err(PubmedIdError::Empty));
+        assert_eq!(PubmedId::parse("000"), Err(PubmedIdError::Empty));
+        assert_eq!(PubmedId::parse("3217a076"), Err(PubmedIdError::InvalidCharacter('a')));
+        assert_eq!(PubmedId::parse("123456789"), Err(PubmedIdError::TooLong));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(PubmedId::Pmid("1".to_string()).to_string(), "PMID 1");
+        assert_eq!(PubmedId::Pmcid("2".to_string()).to_string(), "PMC2");
+    }
+}
@@ -0,0 +1,312 @@
+// PubMed records through E-utilities, or any server with the same efetch and esearch.
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::date::{month_number, PublicationDate};
+use crate::fetch::{join, FetchError, HttpClient};
+use crate::locator::expand_end;
+use crate::person::{format_people, Person};
+use crate::pmid::PubmedId;
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// The article with that PMID or PMCID, with warnings about what's missing.
+pub fn lookup(client: &dyn HttpClient, base_url: &str, id: &PubmedId, lang: Lang) -> Result<(ApaFormat, Vec<String>), FetchError> {
+    let pmid = match id {
+        PubmedId::Pmid(digits) => digits.clone(),
+        PubmedId::Pmcid(digits) => pmcid_to_pmid(client, base_url, digits)?,
+    };
+    let url = join(base_url, &format!("efetch.fcgi?db=pubmed&id={}&retmode=xml", pmid));
+    let body = client.get(&url, "application/xml")?;
+    let document = parse(&body)?;
+    document.descendants()
+        .find(|node| node.has_tag_name("PubmedArticle"))
+        .map(|article| from_article(article, lang))
+        .ok_or(FetchError::NotFound)
+}
+
+// PMC articles are also in PubMed, esearch finds which record they are.
+fn pmcid_to_pmid(client: &dyn HttpClient, base_url: &str, digits: &str) -> Result<String, FetchError> {
+    let url = join(base_url, &format!("esearch.fcgi?db=pubmed&term=PMC{}%5Bpmcid%5D", digits));
+    let body = client.get(&url, "application/xml")?;
+    let document = parse(&body)?;
+    document.descendants()
+        .find(|node| node.has_tag_name("Id"))
+        .map(text)
+        .filter(|pmid| !pmid.is_empty())
+        .ok_or(FetchError::NotFound)
+}
+
+// The answers start with a <!DOCTYPE>.
+fn parse(body: &str) -> Result<Document<'_>, FetchError> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    Document::parse_with_options(body, options).map_err(|error| FetchError::Invalid(error.to_string()))
+}
+
+// A <PubmedArticle> as a journal article.
+pub fn from_article(article: Node, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let mut apa = ApaFormat::new(ApaFormatType::Journal, Some(lang));
+    let mut warnings = Vec::new();
+    let citation = child(article, &["MedlineCitation", "Article"]);
+
+    let author_list = citation.and_then(|citation| child(citation, &["AuthorList"]));
+    let authors: Vec<Person> = author_list
+        .map(|list| {
+            list.children()
+                .filter(|author| author.has_tag_name("Author") && author.attribute("ValidYN") != Some("N"))
+                .filter_map(author)
+                .collect()
+        })
+        .unwrap_or_default();
+    if author_list.and_then(|list| list.attribute("CompleteYN")) == Some("N") {
+        warnings.push("PubMed doesn't list every author, add the rest".to_string());
+    }
+    apa.set("authors", format_people(&authors));
+
+    let issue = citation.and_then(|citation| child(citation, &["Journal", "JournalIssue"]));
+    let published = issue.and_then(|issue| child(issue, &["PubDate"])).and_then(read_date);
+    // Articles ahead of print only have the date they were put online.
+    let online = citation.and_then(|citation| child(citation, &["ArticleDate"])).and_then(read_date);
+    if let Some(date) = published.or(online) {
+        apa.set_date(&date);
+    }
+
+    let title = citation.and_then(|citation| child(citation, &["ArticleTitle"])).map(text).unwrap_or_default();
+    // Titles in other languages are translated by PubMed and put in brackets.
+    let title = match title.strip_prefix('[').and_then(|title| title.trim_end_matches('.').strip_suffix(']')) {
+        Some(translated) => {
+            warnings.push("PubMed only has the English translation of the title, add the original".to_string());
+            translated.to_string()
+        }
+        None => title.trim_end_matches('.').to_string(),
+    };
+    apa.set("title", title);
+
+    let journal = citation.and_then(|citation| child(citation, &["Journal"]));
+    let full_title = journal.and_then(|journal| child(journal, &["Title"])).map(text).unwrap_or_default();
+    if full_title.is_empty() {
+        let abbreviation = journal.and_then(|journal| child(journal, &["ISOAbbreviation"])).map(text).unwrap_or_default();
+        if !abbreviation.is_empty() {
+            warnings.push(format!("PubMed only has \"{}\", write the full journal title", abbreviation));
+        }
+        apa.set("journal", abbreviation);
+    } else {
+        let title = journal_title(&full_title);
+        // The place in parentheses tells journals apart in MEDLINE, it sometimes hides a "The".
+        if full_title.ends_with(')') && !title.starts_with("The ") {
+            warnings.push(format!("PubMed calls the journal \"{}\", check the title the journal uses", full_title));
+        }
+        apa.set("journal", title);
+    }
+
+    let field = |name: &str| issue.and_then(|issue| child(issue, &[name])).map(text).unwrap_or_default();
+    apa.set("volume", field("Volume"));
+    apa.set("issue", field("Issue"));
+
+    let pages = citation.and_then(|citation| child(citation, &["Pagination", "MedlinePgn"])).map(text).unwrap_or_default();
+    apa.set("pages", expand_pages(&pages));
+
+    // The DOI is in the article ids, or in ELocationID for articles without pages.
+    let doi = article.descendants()
+        .find(|node| node.has_tag_name("ArticleId") && node.attribute("IdType") == Some("doi"))
+        .or_else(|| {
+            citation?.children().find(|node| node.has_tag_name("ELocationID") && node.attribute("EIdType") == Some("doi"))
+        })
+        .map(text)
+        .unwrap_or_default();
+    apa.set("doi", doi);
+
+    (apa, warnings)
+}
+
+// MEDLINE names: LastName with ForeName or Initials, or a CollectiveName for groups.
+fn author(node: Node) -> Option<Person> {
+    let field = |name: &str| child(node, &[name]).map(text).unwrap_or_default();
+    let collective = field("CollectiveName");
+    if !collective.is_empty() {
+        return Some(Person::new(collective, ""));
+    }
+    let family = field("LastName");
+    if family.is_empty() {
+        return None;
+    }
+    let given = match field("ForeName") {
+        // "JA" is J. A.
+        fore_name if fore_name.is_empty() => field("Initials").chars().map(|c| format!("{}.", c)).collect::<Vec<String>>().join(" "),
+        fore_name => fore_name,
+    };
+    Some(Person::new(family, given))
+}
+
+// <PubDate> has a Year, a Month and a Day, or only a MedlineDate like "1998 Dec-1999 Jan".
+fn read_date(node: Node) -> Option<PublicationDate> {
+    let field = |name: &str| child(node, &[name]).map(text).unwrap_or_default();
+    let year = match field("Year").parse() {
+        Ok(year) => year,
+        Err(_) => return PublicationDate::parse(field("MedlineDate").get(..4)?),
+    };
+    let month = field("Month");
+    let month = month.parse().ok().or_else(|| month_number(&month));
+    let day = field("Day").parse().ok().filter(|_| month.is_some());
+    Some(PublicationDate { year, month, day })
+}
+
+// PubMed writes journal titles in lowercase with what tells them apart,
+// "The New England journal of medicine" or "Lancet (London, England)".
+pub fn journal_title(title: &str) -> String {
+    const SMALL: [&str; 19] = [
+        "a", "an", "and", "at", "by", "for", "in", "of", "on", "the", "to",
+        "de", "del", "el", "en", "la", "las", "los", "y",
+    ];
+    // Journals whose "The" MEDLINE leaves out.
+    const WITH_THE: [&str; 1] = ["Lancet"];
+    // The place first, it can have a " : " too, "(Bethesda, Md. : 1985)".
+    let title = match title.rfind(" (") {
+        Some(start) if title.ends_with(')') => &title[..start],
+        _ => title,
+    };
+    let title = title.split(" : ").next().unwrap_or(title);
+    if WITH_THE.contains(&title) {
+        return format!("The {}", title);
+    }
+    title.split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            if i > 0 && SMALL.contains(&word) {
+                word.to_string()
+            } else {
+                let mut chars = word.chars();
+                chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// "123-9" is 123-129.
+fn expand_pages(pages: &str) -> String {
+    let Some((start, end)) = pages.split_once('-') else {
+        return pages.to_string();
+    };
+    format!("{}-{}", start, expand_end(start, end))
+}
+
+// The element at the end of the path of child names.
+fn child<'a, 'input>(node: Node<'a, 'input>, path: &[&str]) -> Option<Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| node.children().find(|child| child.has_tag_name(*name)))
+}
+
+// All the text inside, titles can have <i> and <sup> in them.
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter_map(|node| node.text().filter(|_| node.is_text()))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FakeClient;
+
+    const BASE: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+
+    fn client() -> FakeClient {
+        FakeClient::new(&[
+            (
+                "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/efetch.fcgi?db=pubmed&id=32171076&retmode=xml",
+                include_str!("../../tests/fixtures/pubmed/efetch.xml"),
+            ),
+            (
+                "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/efetch.fcgi?db=pubmed&id=10051234&retmode=xml",
+                include_str!("../../tests/fixtures/pubmed/efetch-medline-date.xml"),
+            ),
+            (
+                "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/esearch.fcgi?db=pubmed&term=PMC7270627%5Bpmcid%5D",
+                include_str!("../../tests/fixtures/pubmed/esearch.xml"),
+            ),
+        ])
+    }
+
+    fn lookup_id(id: &str) -> Result<(ApaFormat, Vec<String>), FetchError> {
+        lookup(&client(), BASE, &PubmedId::parse(id).unwrap(), Lang::English)
+    }
+
+    #[test]
+    fn efetch() {
+        let (apa, warnings) = lookup_id("32171076").unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(apa.get("authors"), "Zhou, F., Yu, T., & China Medical Treatment Expert Group for Covid-19");
+        assert_eq!(apa.get("date"), "2020");
+        assert_eq!(apa.get("title"), "Clinical course and risk factors for mortality of adult inpatients with COVID-19 in Wuhan, China: a retrospective cohort study");
+        assert_eq!(apa.get("journal"), "The Lancet");
+        assert_eq!((apa.get("volume"), apa.get("issue"), apa.get("pages")), ("395", "10229", "1054-1062"));
+        assert_eq!(apa.get("doi"), "10.1016/S0140-6736(20)30566-3");
+    }
+
+    #[test]
+    fn pmcids_go_through_esearch() {
+        let (apa, _) = lookup_id("PMC7270627").unwrap();
+        assert_eq!(apa.get("journal"), "The Lancet");
+    }
+
+    #[test]
+    fn medline_dates_and_what_pubmed_lacks() {
+        let (apa, warnings) = lookup_id("10051234").unwrap();
+        assert_eq!(apa.get("date"), "1998");
+        assert_eq!(apa.get("title"), "The health of the population");
+        assert_eq!(apa.get("journal"), "Rev Esp Salud Publica");
+        assert_eq!(apa.get("pages"), "123-129");
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        let client = FakeClient::new(&[(
+            "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/efetch.fcgi?db=pubmed&id=1&retmode=xml",
+            "<?xml version=\"1.0\" ?>\n<!DOCTYPE PubmedArticleSet>\n<PubmedArticleSet></PubmedArticleSet>",
+        )]);
+        let error = lookup(&client, BASE, &PubmedId::Pmid("1".to_string()), Lang::English).err();
+        assert_eq!(error, Some(FetchError::NotFound));
+        assert_eq!(lookup_id("PMC1").err(), Some(FetchError::NotFound));
+    }
+
+    #[test]
+    fn medline_date() {
+        let xml = "<PubDate><MedlineDate>2000 Spring</MedlineDate></PubDate>";
+        let document = Document::parse(xml).unwrap();
+        assert_eq!(read_date(document.root_element()), Some(PublicationDate { year: 2000, month: None, day: None }));
+        let xml = "<PubDate><Year>2001</Year><Month>Sep</Month><Day>4</Day></PubDate>";
+        let document = Document::parse(xml).unwrap();
+        assert_eq!(read_date(document.root_element()), Some(PublicationDate { year: 2001, month: Some(9), day: Some(4) }));
+        let document = Document::parse("<PubDate></PubDate>").unwrap();
+        assert_eq!(read_date(document.root_element()), None);
+    }
+
+    #[test]
+    fn journal_titles() {
+        assert_eq!(journal_title("The New England journal of medicine"), "The New England Journal of Medicine");
+        assert_eq!(journal_title("Lancet (London, England)"), "The Lancet");
+        assert_eq!(journal_title("Journal of applied physiology (Bethesda, Md. : 1985)"), "Journal of Applied Physiology");
+        assert_eq!(journal_title("Revista de salud publica (Bogota, Colombia)"), "Revista de Salud Publica");
+    }
+
+    #[test]
+    fn other_places_in_parentheses_warn() {
+        let xml = "<PubmedArticle><MedlineCitation><Article><Journal><Title>Science (New York, N.Y.)</Title></Journal></Article></MedlineCitation></PubmedArticle>";
+        let document = Document::parse(xml).unwrap();
+        let (apa, warnings) = from_article(document.root_element(), Lang::English);
+        assert_eq!(apa.get("journal"), "Science");
+        assert!(warnings.iter().any(|warning| warning.contains("Science (New York, N.Y.)")));
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(expand_pages("123-9"), "123-129");
+        assert_eq!(expand_pages("1054-62"), "1054-1062");
+        assert_eq!(expand_pages("12-345"), "12-345");
+        assert_eq!(expand_pages("e1001"), "e1001");
+        assert_eq!(expand_pages("S12-9"), "S12-9");
+    }
+}
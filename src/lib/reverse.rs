@@ -0,0 +1,447 @@
+// Formatted references (APA or MLA) pasted by the user, back into fields.
+// It's a guess: the fields it isn't sure about are marked for review.
+
+use crate::date::PublicationDate;
+use crate::doi::Doi;
+use crate::person::{format_editors, format_people, is_initials, parse_people, Person};
+use crate::url::Url;
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+pub struct ParsedReference {
+    pub apa: ApaFormat,
+    /// Ids of the fields that should be checked.
+    pub review: Vec<&'static str>,
+    pub warnings: Vec<String>,
+}
+
+// Fields found in the text, before the format is known.
+#[derive(Debug, Default)]
+struct Parts {
+    authors: Vec<Person>,
+    date: String,
+    title: String,
+    container: String,
+    editors: Vec<Person>,
+    volume: String,
+    issue: String,
+    pages: String,
+    edition: String,
+    publisher: String,
+    retrieved: String,
+    review: Vec<&'static str>,
+}
+
+pub fn parse(text: &str, lang: Lang) -> ParsedReference {
+    let mut text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let doi = take_doi(&mut text);
+    let url = take_url(&mut text);
+    let mut parts = Parts::default();
+    let mut warnings = Vec::new();
+
+    // "Retrieved May 3, 2020, from" is left before the URL.
+    for phrase in ["Retrieved ", "Retrived ", "Consultado el ", "Recuperado el ", "Accessed "] {
+        if let Some(start) = text.find(phrase) {
+            let date = text[start + phrase.len()..].trim_end_matches(['.', ' ']);
+            let date = date.trim_end_matches(" from").trim_end_matches(", de").trim_end_matches(" de").trim_end_matches(',');
+            parts.retrieved = date.to_string();
+            text.truncate(start);
+        }
+    }
+    for phrase in ["Recuperado de", "Retrieved from"] {
+        if let Some(start) = text.find(phrase) {
+            text.truncate(start);
+        }
+    }
+    let text = text.trim().to_string();
+
+    let mla = match apa_date(&text) {
+        Some((open, close)) => {
+            read_apa(&text, open, close, &mut parts);
+            false
+        }
+        None => {
+            read_mla(&text, &mut parts);
+            true
+        }
+    };
+
+    let format = guess_format(&parts, url.is_some());
+    // In APA the publisher of a book is where the site name would be.
+    if format == ApaFormatType::Book && parts.publisher.is_empty() {
+        parts.publisher = std::mem::take(&mut parts.container);
+    }
+    let mut apa = ApaFormat::new(format, Some(lang));
+    if parts.title.is_empty() {
+        // Nothing could be read, at least the text isn't lost.
+        parts.authors.clear();
+        parts.title = text.clone();
+        warnings.push("couldn't read the reference, check every field".to_string());
+    } else {
+        warnings.push(format!("read as a {}{}, check the marked fields", format, if mla { " in MLA style" } else { "" }));
+    }
+
+    apa.set("authors", format_people(&parts.authors));
+    apa.set("editors", format_editors(&parts.editors));
+    match PublicationDate::parse(&parts.date) {
+        Some(date) => {
+            apa.set_date(&date);
+        }
+        None => {
+            apa.set("date", parts.date.as_str());
+        }
+    }
+    if !apa.set("title", parts.title.as_str()) {
+        apa.set("word", parts.title.as_str());
+    }
+    if let Some(id) = format.container() {
+        apa.set(id, parts.container.as_str());
+    }
+    apa.set("volume", parts.volume.as_str());
+    apa.set("issue", parts.issue.as_str());
+    apa.set("pages", parts.pages.as_str());
+    apa.set("edition", parts.edition.as_str());
+    apa.set("publisher", parts.publisher.as_str());
+    if let Some(doi) = doi {
+        apa.set("doi", doi);
+    }
+    if let Some(url) = url {
+        apa.set("url", url);
+    }
+    if let Some(date) = PublicationDate::parse(&parts.retrieved) {
+        apa.set("retrieved", date.apa(&lang));
+    }
+
+    // Only the fields the format has, and only once.
+    let mut review: Vec<&'static str> = Vec::new();
+    for id in parts.review {
+        let id = match id {
+            "container" => format.container().unwrap_or("publisher"),
+            "title" if format == ApaFormatType::Dictionary => "word",
+            _ => id,
+        };
+        if apa.index_of(id).is_some() && !review.contains(&id) {
+            review.push(id);
+        }
+    }
+
+    ParsedReference { apa, review, warnings }
+}
+
+fn guess_format(parts: &Parts, has_url: bool) -> ApaFormatType {
+    let container = parts.container.to_lowercase();
+    if !parts.volume.is_empty() || !parts.issue.is_empty() {
+        ApaFormatType::Journal
+    } else if container.contains("dictionary") || container.contains("diccionario") || container.contains("encyclopedia") {
+        ApaFormatType::Dictionary
+    } else if !parts.editors.is_empty() || (!parts.container.is_empty() && !parts.publisher.is_empty()) {
+        ApaFormatType::Chapter
+    } else if has_url && !parts.container.is_empty() {
+        // Newspapers have the full date, most websites too, so only the name tells them apart.
+        const NEWS: &[&str] = &["times", "post", "news", "herald", "guardian", "tribune", "daily", "gazette", "país", "mundo", "nación"];
+        if NEWS.iter().any(|word| container.contains(word)) { ApaFormatType::Newspaper } else { ApaFormatType::Website }
+    } else if !parts.publisher.is_empty() || !has_url {
+        ApaFormatType::Book
+    } else {
+        ApaFormatType::Website
+    }
+}
+
+/* APA */
+
+// Where "(2020)." or "(2020, May 3)." is, the authors are before it.
+fn apa_date(text: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    while let Some(open) = text[from..].find('(').map(|open| open + from) {
+        let close = text[open..].find(')').map(|close| close + open)?;
+        let inside = &text[open + 1..close];
+        let year = inside.split(|c: char| !c.is_ascii_digit()).any(|part| part.len() == 4);
+        if year || inside == "n.d." || inside == "s.f." {
+            return Some((open, close));
+        }
+        from = close;
+    }
+    None
+}
+
+fn read_apa(text: &str, open: usize, close: usize, parts: &mut Parts) {
+    let authors = text[..open].trim();
+    // "Organization." keeps its dot out, "Smith, J." keeps it.
+    let authors = match authors.rsplit(' ').next() {
+        Some(last) if is_initials(last) => authors,
+        _ => authors.trim_end_matches('.'),
+    };
+    parts.authors = parse_people(authors);
+    if parts.authors.iter().any(Person::is_organization) {
+        parts.review.push("authors");
+    }
+    parts.date = text[open + 1..close].to_string();
+
+    let rest = text[close + 1..].trim_start_matches('.').trim();
+    let (title, rest) = sentence(rest);
+    parts.title = title;
+
+    // "(2nd ed.)" after a book title, the title can have its own parentheses.
+    if parts.title.ends_with("ed.)") || parts.title.ends_with("ed)") {
+        if let Some(start) = parts.title.rfind(" (") {
+            parts.edition = parts.title[start + 2..parts.title.len() - 1].to_string();
+            parts.title.truncate(start);
+        }
+    }
+
+    if let Some(book) = rest.strip_prefix("In ") {
+        read_in(book, parts);
+    } else if !read_periodical(rest, parts) {
+        // "Site name." or "Publisher.", only the format tells which.
+        let (container, rest) = sentence(rest);
+        parts.container = container;
+        if !rest.is_empty() {
+            parts.publisher = sentence(rest).0;
+        }
+        if parts.container.is_empty() {
+            parts.review.push("title");
+        } else {
+            parts.review.push("container");
+        }
+    }
+}
+
+// "In A. Editor (Ed.), Book (pp. 1–10). Publisher." or "In Dictionary. Publisher."
+fn read_in(text: &str, parts: &mut Parts) {
+    let (editors, book) = match text.find(" (Ed") {
+        Some(start) => {
+            let after = text[start..].find("), ").map_or(text.len(), |end| start + end + 3);
+            (&text[..start], text[after.min(text.len())..].trim())
+        }
+        None => ("", text),
+    };
+    parts.editors = parse_people(editors);
+
+    let (book, rest) = match book.find(" (p") {
+        Some(start) => {
+            let end = book[start..].find(')').map_or(book.len(), |end| start + end);
+            let pages = &book[start + 2..end];
+            parts.pages = pages.trim_start_matches("pp. ").trim_start_matches("p. ").to_string();
+            (&book[..start], book[(end + 1).min(book.len())..].trim_start_matches('.').trim())
+        }
+        None => {
+            let (book, rest) = sentence(book);
+            parts.container = book;
+            parts.publisher = sentence(rest).0;
+            parts.review.push("publisher");
+            return;
+        }
+    };
+    parts.container = book.trim().to_string();
+    parts.publisher = sentence(rest).0;
+}
+
+// "Journal, 12(3), 45–67." Returns false if it isn't like that.
+fn read_periodical(text: &str, parts: &mut Parts) -> bool {
+    let mut from = 0;
+    while let Some(comma) = text[from..].find(", ").map(|comma| comma + from) {
+        let after = &text[comma + 2..];
+        let volume: String = after.chars().take_while(char::is_ascii_digit).collect();
+        if volume.is_empty() {
+            from = comma + 2;
+            continue;
+        }
+        let mut rest = &after[volume.len()..];
+        let mut issue = String::new();
+        if let Some(inside) = rest.strip_prefix('(') {
+            // "Journal, 12(" was cut before the issue.
+            match inside.find(')') {
+                Some(close) => {
+                    issue = inside[..close].to_string();
+                    rest = &inside[close + 1..];
+                }
+                None => rest = inside,
+            }
+        }
+        if !(rest.is_empty() || rest.starts_with(',') || rest.starts_with('.')) {
+            from = comma + 2;
+            continue;
+        }
+        parts.container = text[..comma].to_string();
+        parts.volume = volume;
+        parts.issue = issue;
+        parts.pages = rest.trim_start_matches(',').trim().trim_end_matches('.').to_string();
+        return true;
+    }
+    false
+}
+
+/* MLA */
+
+// Last, First, and First Last. "Title." Container, vol. 1, no. 2, 2020, pp. 3-4.
+fn read_mla(text: &str, parts: &mut Parts) {
+    let (authors, rest) = match text.find(" \"").or_else(|| text.find(" “")) {
+        Some(quote) => (text[..quote].trim().to_string(), text[quote..].trim()),
+        None => sentence(text),
+    };
+    parts.authors = mla_people(authors.trim_end_matches('.'));
+    parts.review.push("authors");
+
+    let quoted = rest.starts_with(['"', '“']);
+    let rest = if quoted {
+        // The quoted title is the article, the chapter or the page.
+        let rest = &rest[rest.chars().next().unwrap().len_utf8()..];
+        let end = rest.find(['"', '”']).unwrap_or(rest.len());
+        parts.title = rest[..end].trim().trim_end_matches(['.', ',']).to_string();
+        let after = &rest[end..];
+        after[after.chars().next().map_or(0, char::len_utf8)..].trim()
+    } else {
+        let (title, rest) = sentence(rest);
+        parts.title = title;
+        rest
+    };
+    parts.review.push("title");
+
+    // The container and the rest are separated by commas.
+    let pieces: Vec<&str> = rest.trim_end_matches('.').split(", ").map(str::trim).collect();
+    for (i, piece) in pieces.iter().enumerate() {
+        if let Some(volume) = piece.strip_prefix("vol. ") {
+            parts.volume = volume.to_string();
+        } else if let Some(issue) = piece.strip_prefix("no. ") {
+            parts.issue = issue.to_string();
+        } else if let Some(pages) = piece.strip_prefix("pp. ").or_else(|| piece.strip_prefix("p. ")) {
+            parts.pages = pages.trim_end_matches('.').to_string();
+        } else if let Some(editors) = piece.strip_prefix("edited by ") {
+            parts.editors = mla_people(editors);
+        } else if PublicationDate::parse(piece).is_some() && parts.date.is_empty() {
+            parts.date = piece.to_string();
+        } else if i == 0 {
+            parts.container = piece.trim_end_matches('.').to_string();
+        } else if parts.publisher.is_empty() {
+            parts.publisher = piece.to_string();
+        }
+    }
+    // A book has no quotes, "Title. Publisher, 2020."
+    if !quoted && parts.volume.is_empty() && parts.editors.is_empty() && parts.publisher.is_empty() {
+        parts.publisher = std::mem::take(&mut parts.container);
+    }
+    parts.review.extend(["date", "container", "publisher"]);
+}
+
+// "Smith, John, and Jane Doe", only the first author is written last name first.
+fn mla_people(text: &str) -> Vec<Person> {
+    let text = text.replace(", and ", ", ").replace(" and ", ", ");
+    let text = text.trim_end_matches(", et al").trim_end_matches(" et al");
+    let mut names = text.split(", ").map(str::trim).filter(|name| !name.is_empty());
+    let mut people = Vec::new();
+    if let (Some(family), Some(given)) = (names.next(), names.next()) {
+        // "Smith, John" is one person, "Smith, Jane Doe" would be two.
+        if given.split_whitespace().count() == 1 || is_initials(given) {
+            people.push(Person::new(family, given));
+        } else {
+            people.push(Person::from_full_name(family));
+            people.push(Person::from_full_name(given));
+        }
+    } else if let Some(name) = text.split(", ").next().filter(|name| !name.is_empty()) {
+        people.push(Person::new(name, ""));
+    }
+    people.extend(names.map(Person::from_full_name));
+    people
+}
+
+/* Shared */
+
+// The first sentence and the rest. Dots after initials ("J. K.") don't end it.
+fn sentence(text: &str) -> (String, &str) {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    for (i, (position, c)) in chars.iter().enumerate() {
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        if !matches!(c, '.' | '?' | '!') || !matches!(next, Some(' ') | None) {
+            continue;
+        }
+        // "J." and "ed." are abbreviations, not the end.
+        let word_start = text[..*position].rfind(' ').map_or(0, |space| space + 1);
+        let word = &text[word_start..*position];
+        if *c == '.' && (word.chars().count() == 1 && word.chars().all(char::is_uppercase) || matches!(word, "ed" | "Ed" | "Eds" | "pp" | "vol" | "no" | "p")) {
+            continue;
+        }
+        let end = if *c == '.' { *position } else { position + c.len_utf8() };
+        return (text[..end].trim().to_string(), text[position + c.len_utf8()..].trim());
+    }
+    (text.trim().trim_end_matches('.').to_string(), "")
+}
+
+// The DOI, as a link or "doi:10...", taken out of the text.
+fn take_doi(text: &mut String) -> Option<String> {
+    let word = text.split(' ')
+        .find(|word| word.contains("doi.org/") || word.starts_with("doi:") || word.starts_with("10."))?
+        .to_string();
+    let doi = Doi::parse(word.trim_end_matches('.')).ok()?;
+    *text = text.replacen(&word, "", 1);
+    Some(doi.to_string())
+}
+
+fn take_url(text: &mut String) -> Option<String> {
+    let word = text.split(' ').find(|word| word.starts_with("http://") || word.starts_with("https://"))?.to_string();
+    let url = Url::parse(word.trim_end_matches('.')).ok()?;
+    *text = text.replacen(&word, "", 1);
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> ApaFormat {
+        parse(text, Lang::English).apa
+    }
+
+    #[test]
+    fn journal_article() {
+        let apa = read("Bandura, A. (1977). Self-efficacy: Toward a unifying theory of behavioral change. Psychological Review, 84(2), 191–215. https://doi.org/10.1037/0033-295X.84.2.191");
+        assert!(apa.format == ApaFormatType::Journal);
+        assert_eq!(apa.get("authors"), "Bandura, A.");
+        assert_eq!(apa.get("date"), "1977");
+        assert_eq!(apa.get("title"), "Self-efficacy: Toward a unifying theory of behavioral change");
+        assert_eq!(apa.get("journal"), "Psychological Review");
+        assert_eq!((apa.get("volume"), apa.get("issue"), apa.get("pages")), ("84", "2", "191–215"));
+        assert_eq!(apa.get("doi"), "10.1037/0033-295X.84.2.191");
+    }
+
+    #[test]
+    fn unclosed_issues_dont_panic() {
+        let apa = read("Smith, J. (2020). A title. Journal, 12(");
+        assert_eq!((apa.get("journal"), apa.get("volume"), apa.get("issue")), ("Journal", "12", ""));
+
+        // The last character is more than one byte.
+        let apa = read("Smith, J. (2020). A title. Revista, 12(é");
+        assert_eq!(apa.get("issue"), "");
+        read("Smith, J. (2020). A title. Revista, 12(ü, 3–4.");
+    }
+
+    #[test]
+    fn editions_after_titles_with_parentheses() {
+        let apa = read("Field, A. (2018). Discovering statistics (and sex and drugs) using IBM SPSS statistics (5th ed.). SAGE.");
+        assert!(apa.format == ApaFormatType::Book);
+        assert_eq!(apa.get("title"), "Discovering statistics (and sex and drugs) using IBM SPSS statistics");
+        assert_eq!(apa.get("edition"), "5th ed.");
+        assert_eq!(apa.get("publisher"), "SAGE");
+
+        // Parentheses that aren't an edition stay in the title.
+        let apa = read("Field, A. (2018). Statistics (for everyone). SAGE.");
+        assert_eq!(apa.get("title"), "Statistics (for everyone)");
+        assert_eq!(apa.get("edition"), "");
+    }
+
+    #[test]
+    fn chapters() {
+        let apa = read("Smith, J., & Pérez, M. (2019). A chapter. In A. Editor & B. Editor (Eds.), The book (pp. 10–20). Publisher.");
+        assert!(apa.format == ApaFormatType::Chapter);
+        assert_eq!(apa.get("authors"), "Smith, J., & Pérez, M.");
+        assert_eq!(apa.get("editors"), "A. Editor & B. Editor");
+        assert_eq!(apa.get("book"), "The book");
+        assert_eq!(apa.get("pages"), "10–20");
+        assert_eq!(apa.get("publisher"), "Publisher");
+    }
+
+    #[test]
+    fn text_that_isnt_a_reference_is_kept() {
+        let parsed = parse("just some words", Lang::English);
+        assert_eq!(parsed.apa.get("title"), "just some words");
+        assert_eq!(parsed.warnings, ["couldn't read the reference, check every field"]);
+    }
+}
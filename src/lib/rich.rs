@@ -0,0 +1,335 @@
+// Rich text model of a reference, so it can be printed to more than html.
+
+use termion::style;
+
+use crate::url::Url;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Span {
+    Plain(String),
+    Italic(String),
+    Link(String),
+}
+
+// A reference is just a list of spans, each backend decides how they look.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+}
+
+impl RichText {
+    pub fn new() -> RichText {
+        RichText { spans: Vec::new() }
+    }
+    pub fn plain(&mut self, text: impl Into<String>) -> &mut Self {
+        self.spans.push(Span::Plain(text.into()));
+        self
+    }
+    pub fn italic(&mut self, text: impl Into<String>) -> &mut Self {
+        self.spans.push(Span::Italic(text.into()));
+        self
+    }
+    pub fn link(&mut self, url: impl Into<String>) -> &mut Self {
+        self.spans.push(Span::Link(url.into()));
+        self
+    }
+
+    // Render the reference using any of the backends.
+    pub fn render(&self, backend: &dyn Backend) -> String {
+        backend.render(self)
+    }
+}
+
+pub trait Backend {
+    /// Text without any style.
+    fn plain(&self, text: &str) -> String;
+    /// Titles and such that APA wants in italics.
+    fn italic(&self, text: &str) -> String;
+    /// URLs (and DOIs) to the source.
+    fn link(&self, url: &str) -> String;
+
+    fn render(&self, text: &RichText) -> String {
+        text.spans.iter()
+            .map(|span| match span {
+                Span::Plain(text) => self.plain(text),
+                Span::Italic(text) => self.italic(text),
+                Span::Link(url) => self.link(url),
+            })
+            .collect()
+    }
+}
+
+// Used for the clipboard.
+pub struct Html;
+impl Backend for Html {
+    fn plain(&self, text: &str) -> String {
+        escape_html(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("<i>{}</i>", escape_html(text))
+    }
+    fn link(&self, url: &str) -> String {
+        // Only web links are clickable, anything else (like a placeholder) is just text.
+        if url.starts_with("https://") || url.starts_with("http://") {
+            format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(url))
+        } else {
+            escape_html(url)
+        }
+    }
+}
+
+// Used for the preview inside of the terminal.
+pub struct Ansi;
+impl Backend for Ansi {
+    fn plain(&self, text: &str) -> String {
+        strip_control(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("{}{}{}", style::Italic, strip_control(text), style::NoItalic)
+    }
+    fn link(&self, url: &str) -> String {
+        format!("{}{}{}", style::Underline, strip_control(url), style::NoUnderline)
+    }
+}
+
+impl Ansi {
+    // Split the reference in lines that fit the terminal. Words are kept
+    // whole and long URLs are only broken where it's safe to do so.
+    pub fn wrap(&self, text: &RichText, width: usize) -> Vec<String> {
+        let width = width.max(1);
+
+        // Pieces that can't be split, with the span they came from.
+        let mut pieces: Vec<(&Span, String)> = Vec::new();
+        for span in &text.spans {
+            match span {
+                Span::Plain(text) | Span::Italic(text) => {
+                    for word in strip_control(text).split_inclusive(' ') {
+                        pieces.push((span, word.to_string()));
+                    }
+                }
+                Span::Link(url) => {
+                    let url = strip_control(url);
+                    let mut start = 0;
+                    for point in Url::break_points(&url).into_iter().chain([url.len()]) {
+                        pieces.push((span, url[start..point].to_string()));
+                        start = point;
+                    }
+                }
+            }
+        }
+
+        // Fill the lines, cutting pieces that don't fit in a whole line.
+        let mut lines: Vec<Vec<(&Span, String)>> = vec![Vec::new()];
+        let mut line_width = 0;
+        for (span, mut piece) in pieces {
+            loop {
+                let piece_width = piece.chars().count();
+                let trimmed_width = piece.trim_end().chars().count();
+                if line_width + trimmed_width <= width {
+                    line_width += piece_width;
+                    lines.last_mut().unwrap().push((span, piece));
+                    break;
+                }
+                if line_width > 0 {
+                    lines.push(Vec::new());
+                    line_width = 0;
+                    continue;
+                }
+                // Too long for any line.
+                let cut = piece.char_indices().nth(width).map(|(i, _)| i).unwrap_or(piece.len());
+                let rest = piece.split_off(cut);
+                lines.last_mut().unwrap().push((span, piece));
+                lines.push(Vec::new());
+                piece = rest;
+            }
+        }
+
+        lines.iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let last = line.len() - 1;
+                line.iter()
+                    .enumerate()
+                    .map(|(i, (span, piece))| {
+                        let piece = if i == last { piece.trim_end() } else { piece.as_str() };
+                        match span {
+                            Span::Plain(_) => self.plain(piece),
+                            Span::Italic(_) => self.italic(piece),
+                            Span::Link(_) => self.link(piece),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// How plain text shows the italics, for places that can't take html.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Italics {
+    /// Italics are lost.
+    #[default]
+    None,
+    /// 𝑀𝑎𝑡ℎ𝑒𝑚𝑎𝑡𝑖𝑐𝑎𝑙 italic letters, they survive in chat apps.
+    Unicode,
+    /// _Title_, like emails and commit messages do.
+    Underscores,
+}
+
+#[derive(Default)]
+pub struct Plain {
+    pub italics: Italics,
+}
+impl Plain {
+    pub fn new(italics: Italics) -> Plain {
+        Plain { italics }
+    }
+}
+impl Backend for Plain {
+    fn plain(&self, text: &str) -> String {
+        strip_control(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        let text = strip_control(text);
+        match self.italics {
+            Italics::None => text,
+            Italics::Unicode => text.chars().map(unicode_italic).collect(),
+            Italics::Underscores if text.is_empty() => text,
+            Italics::Underscores => format!("_{}_", text),
+        }
+    }
+    fn link(&self, url: &str) -> String {
+        strip_control(url)
+    }
+}
+
+// Mathematical italic version of a latin letter, anything else stays the same.
+fn unicode_italic(c: char) -> char {
+    let offset = match c {
+        // Planck's constant was there before the rest of the alphabet.
+        'h' => return 'ℎ',
+        'A'..='Z' => 0x1D434 + (c as u32 - 'A' as u32),
+        'a'..='z' => 0x1D44E + (c as u32 - 'a' as u32),
+        _ => return c,
+    };
+    char::from_u32(offset).unwrap_or(c)
+}
+
+pub struct Markdown;
+impl Backend for Markdown {
+    fn plain(&self, text: &str) -> String {
+        escape_markdown(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("*{}*", escape_markdown(text))
+    }
+    fn link(&self, url: &str) -> String {
+        // Only web links are autolinks, a placeholder like "URL" is text.
+        // They can't contain spaces or angle brackets either.
+        if Url::parse(url).is_ok() && !url.contains(['<', '>']) {
+            format!("<{}>", url)
+        } else {
+            escape_markdown(url)
+        }
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+// Pandoc gives meaning to more characters than plain Markdown: @ starts a
+// citation, $ math, ^ and ~ super and subscripts.
+pub fn escape_markdown(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in strip_control(text).chars() {
+        if "\\`*_[]<>|#@$^~".contains(c) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+// Remove escape codes and other control characters the user may have pasted,
+// so they can't mess with the terminal.
+pub fn strip_control(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_autolinks_only_web_links() {
+        assert_eq!(Markdown.link("https://doi.org/10.1000/xyz"), "<https://doi.org/10.1000/xyz>");
+        assert_eq!(Markdown.link("URL"), "URL");
+        assert_eq!(Markdown.link("ftp://example.org/file"), "ftp://example.org/file");
+        assert_eq!(Markdown.link("https://example.org/<script>"), "https://example.org/\\<script\\>");
+        assert_eq!(Markdown.link("not a link"), "not a link");
+    }
+
+    #[test]
+    fn unicode_italics() {
+        let plain = Plain::new(Italics::Unicode);
+        assert_eq!(plain.italic("Az"), "\u{1D434}\u{1D467}");
+        // Planck's constant fills the hole of h.
+        assert_eq!(plain.italic("hi"), "ℎ\u{1D456}");
+        // Digits, punctuation and accents have no italic form.
+        assert_eq!(plain.italic("Vol. 2: ñ!"), "\u{1D449}\u{1D45C}\u{1D459}. 2: ñ!");
+        assert_eq!(plain.plain("Az"), "Az");
+    }
+
+    #[test]
+    fn underscore_italics() {
+        let plain = Plain::new(Italics::Underscores);
+        assert_eq!(plain.italic("A title"), "_A title_");
+        assert_eq!(plain.italic(""), "");
+        assert_eq!(plain.italic("Tab\tbed"), "_Tabbed_");
+        assert_eq!(Plain::default().italic("A title"), "A title");
+    }
+
+    fn preview() -> RichText {
+        let mut text = RichText::new();
+        text.plain("Smith, J. (2020). ").italic("A long title").plain(". ").link("https://example.org/a/very-long/path?x=1");
+        text
+    }
+
+    // The lines without the escape codes of the styles.
+    fn unstyled(lines: &[String]) -> Vec<String> {
+        lines.iter()
+            .map(|line| {
+                [style::Italic.to_string(), style::NoItalic.to_string(), style::Underline.to_string(), style::NoUnderline.to_string()]
+                    .iter()
+                    .fold(line.clone(), |line, code| line.replace(code, ""))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ansi_wrap_keeps_words_whole() {
+        let lines = Ansi.wrap(&preview(), 24);
+        assert_eq!(unstyled(&lines), ["Smith, J. (2020). A long", "title. https://example", ".org/a/very-long/path?x=", "1"]);
+        assert_eq!(lines[0], format!("Smith, J. (2020). {}A {}{}long{}", style::Italic, style::NoItalic, style::Italic, style::NoItalic));
+    }
+
+    #[test]
+    fn ansi_wrap_cuts_what_never_fits() {
+        let mut text = RichText::new();
+        text.plain("Supercalifragilistic");
+        assert_eq!(unstyled(&Ansi.wrap(&text, 8)), ["Supercal", "ifragili", "stic"]);
+        assert_eq!(unstyled(&Ansi.wrap(&text, 0)).len(), 20);
+    }
+}
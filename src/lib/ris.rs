@@ -0,0 +1,383 @@
+// RIS, what EndNote, Mendeley and Zotero all import.
+
+use crate::date::PublicationDate;
+use crate::import::Candidate;
+use crate::locator::PageLocator;
+use crate::person::{format_editors, format_people, parse_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// TY code of each format.
+pub fn type_code(format: ApaFormatType) -> &'static str {
+    match format {
+        ApaFormatType::Website => "ELEC",
+        ApaFormatType::Newspaper => "NEWS",
+        ApaFormatType::Dictionary => "DICT",
+        ApaFormatType::Journal => "JOUR",
+        ApaFormatType::Chapter => "CHAP",
+        ApaFormatType::Book => "BOOK",
+        ApaFormatType::Preprint => "UNPB",
+        ApaFormatType::None => "GEN",
+    }
+}
+
+// The tags of the record, without TY and ER.
+pub fn tags(apa: &ApaFormat) -> Vec<(&'static str, String)> {
+    let mut tags: Vec<(&'static str, String)> = Vec::new();
+
+    // One line for each author.
+    for person in parse_people(apa.get("authors")) {
+        tags.push(("AU", ris_name(&person.family, &person.given)));
+    }
+
+    match apa.format {
+        ApaFormatType::Dictionary => {
+            tags.push(("TI", apa.get("word").to_string()));
+            tags.push(("T2", apa.get("dictionary").to_string()));
+        }
+        ApaFormatType::Chapter => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("T2", apa.get("book").to_string()));
+        }
+        ApaFormatType::Journal => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("T2", apa.get("journal").to_string()));
+            tags.push(("VL", apa.get("volume").to_string()));
+            tags.push(("IS", apa.get("issue").to_string()));
+        }
+        ApaFormatType::Newspaper => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("T2", apa.get("newspaper").to_string()));
+        }
+        ApaFormatType::Website => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("T2", apa.get("website").to_string()));
+        }
+        ApaFormatType::Book => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("ET", apa.get("edition").to_string()));
+        }
+        ApaFormatType::Preprint => {
+            tags.push(("TI", apa.get("title").to_string()));
+            tags.push(("PB", apa.get("archive").to_string()));
+        }
+        ApaFormatType::None => {}
+    }
+
+    for person in parse_people(apa.get("editors")) {
+        tags.push(("ED", ris_name(&person.family, &person.given)));
+    }
+    tags.push(("PB", apa.get("publisher").to_string()));
+
+    match PageLocator::parse(apa.get("pages")) {
+        Some((PageLocator::Single(page), _)) => tags.push(("SP", page)),
+        Some((PageLocator::Range(start, end), _)) => {
+            tags.push(("SP", start));
+            tags.push(("EP", end));
+        }
+        // Zotero reads the article number from C7.
+        Some((PageLocator::Article(number), _)) => tags.push(("C7", number)),
+        None => {}
+    }
+
+    if let Some(date) = PublicationDate::parse(apa.get("date")) {
+        tags.push(("PY", date.year.to_string()));
+        tags.push(("DA", ris_date(&date)));
+    }
+
+    if let Some(doi) = apa.doi() {
+        tags.push(("DO", doi.to_string()));
+    }
+    if !apa.get("url").is_empty() {
+        tags.push(("UR", apa.url()));
+        // Date it was consulted.
+        if matches!(apa.format, ApaFormatType::Website | ApaFormatType::Newspaper | ApaFormatType::Dictionary) {
+            tags.push(("Y2", ris_date(&apa.retrieved())));
+        }
+    }
+
+    tags.retain(|(_, value)| !value.trim().is_empty());
+    tags
+}
+
+pub fn record(apa: &ApaFormat) -> String {
+    let mut output = format!("TY  - {}\r\n", type_code(apa.format));
+    for (tag, value) in tags(apa) {
+        // A value can't span lines.
+        let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+        output.push_str(&format!("{}  - {}\r\n", tag, value));
+    }
+    output.push_str("ER  - \r\n");
+    output
+}
+
+pub fn file(references: &[ApaFormat]) -> String {
+    references.iter().map(record).collect::<Vec<String>>().join("\r\n")
+}
+
+// "Last, First", the form every reference manager reads back.
+fn ris_name(family: &str, given: &str) -> String {
+    if given.is_empty() { family.to_string() } else { format!("{}, {}", family, given) }
+}
+
+// YYYY/MM/DD/, with the parts that are missing left empty.
+fn ris_date(date: &PublicationDate) -> String {
+    let month = date.month.map(|month| format!("{:02}", month)).unwrap_or_default();
+    let day = date.day.map(|day| format!("{:02}", day)).unwrap_or_default();
+    format!("{:04}/{}/{}/", date.year, month, day)
+}
+
+/* Reading */
+
+// A record as it was in the file, repeated tags (AU, UR...) in order.
+#[derive(Clone, Debug, Default)]
+pub struct RisRecord {
+    pub tags: Vec<(String, String)>,
+}
+
+impl RisRecord {
+    // The first value of the first of the tags that is there.
+    pub fn get(&self, names: &[&str]) -> String {
+        names.iter()
+            .find_map(|name| self.tags.iter().find(|(tag, value)| tag == name && !value.is_empty()))
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    }
+
+    // Every value of the tags, AU and A1 are both authors.
+    pub fn all(&self, names: &[&str]) -> Vec<&str> {
+        self.tags.iter()
+            .filter(|(tag, value)| names.contains(&tag.as_str()) && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+// The records of a file, lines without a tag continue the value above them.
+pub fn parse(text: &str) -> Vec<RisRecord> {
+    let mut records = Vec::new();
+    let mut record: Option<RisRecord> = None;
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim_end();
+        let tagged = line.len() >= 5
+            && line.is_char_boundary(2)
+            && line[..2].chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && line[2..].trim_start().starts_with('-');
+
+        if !tagged {
+            // Values that were wrapped by the program that wrote them.
+            if let Some((_, value)) = record.as_mut().and_then(|record| record.tags.last_mut()) {
+                if !line.trim().is_empty() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            }
+            continue;
+        }
+
+        let tag = &line[..2];
+        let value = line[2..].trim_start()[1..].trim().to_string();
+        match tag {
+            "TY" => record = Some(RisRecord { tags: vec![(tag.to_string(), value)] }),
+            "ER" => records.extend(record.take()),
+            _ => {
+                if let Some(record) = record.as_mut() {
+                    record.tags.push((tag.to_string(), value));
+                }
+            }
+        }
+    }
+    // A file cut before the last ER still has the record.
+    records.extend(record);
+    records
+}
+
+// Which format a record is, and a warning when it's only the closest one.
+pub fn format_of(record: &RisRecord) -> (ApaFormatType, Option<String>) {
+    let code = record.get(&["TY"]);
+    let format = match code.as_str() {
+        "JOUR" | "JFULL" | "EJOUR" | "INPR" => ApaFormatType::Journal,
+        "NEWS" | "MGZN" => ApaFormatType::Newspaper,
+        "ELEC" | "WEB" | "BLOG" => ApaFormatType::Website,
+        "DICT" | "ENCYC" => ApaFormatType::Dictionary,
+        "CHAP" => ApaFormatType::Chapter,
+        "BOOK" | "EBOOK" | "EDBOOK" => ApaFormatType::Book,
+        "UNPB" => ApaFormatType::Preprint,
+        _ => {
+            let format = if !record.get(&["JO", "JF", "JA"]).is_empty() {
+                ApaFormatType::Journal
+            } else if !record.get(&["BT"]).is_empty() || !record.all(&["ED", "A2"]).is_empty() {
+                ApaFormatType::Chapter
+            } else {
+                ApaFormatType::Website
+            };
+            return (format, Some(format!("TY {} opened as a {}, check the fields", code, format)));
+        }
+    };
+    (format, None)
+}
+
+// The record in the editor's fields, with the warnings about what didn't fit.
+pub fn to_apa(record: &RisRecord, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let (format, warning) = format_of(record);
+    let mut apa = ApaFormat::new(format, Some(lang));
+
+    let authors: Vec<Person> = record.all(&["AU", "A1"]).into_iter().map(person).collect();
+    apa.set("authors", format_people(&authors));
+    let editors: Vec<Person> = record.all(&["ED", "A2"]).into_iter().map(person).collect();
+    apa.set("editors", format_editors(&editors));
+
+    if let Some(date) = date(&record.get(&["PY", "Y1"]), &record.get(&["DA"])) {
+        apa.set_date(&date);
+    }
+
+    let title = record.get(&["TI", "T1", "CT"]);
+    if !apa.set("title", title.as_str()) {
+        apa.set("word", title);
+    }
+    let container = match format {
+        ApaFormatType::Journal => record.get(&["T2", "JF", "JO", "JA"]),
+        ApaFormatType::Chapter | ApaFormatType::Dictionary => record.get(&["T2", "BT", "T3"]),
+        _ => record.get(&["T2", "JF", "JO", "PB"]),
+    };
+    if let Some(id) = format.container() {
+        apa.set(id, container);
+    }
+
+    apa.set("volume", record.get(&["VL"]));
+    apa.set("issue", record.get(&["IS"]));
+    apa.set("publisher", record.get(&["PB"]));
+    apa.set("edition", record.get(&["ET"]));
+    let (start, end, article) = (record.get(&["SP"]), record.get(&["EP"]), record.get(&["C7"]));
+    if !start.is_empty() && !end.is_empty() && start != end {
+        apa.set("pages", format!("{}–{}", start, end));
+    } else if !start.is_empty() {
+        apa.set("pages", start);
+    } else if !article.is_empty() {
+        apa.set("pages", format!("Article {}", article));
+    }
+
+    apa.set("doi", record.get(&["DO"]));
+    apa.set("url", record.get(&["UR", "L2"]));
+    if let Some(date) = date(&record.get(&["Y2"]), "") {
+        apa.set("retrieved", date.apa(&lang));
+    }
+
+    (apa, warning.into_iter().collect())
+}
+
+// Every record of a .ris file, ready for the picker.
+pub fn candidates(text: &str, lang: Lang) -> Result<Vec<Candidate>, String> {
+    Ok(parse(text).iter()
+        .map(|record| {
+            let (apa, warnings) = to_apa(record, lang);
+            Candidate::new(&record.get(&["ID"]), apa, warnings)
+        })
+        .collect())
+}
+
+// "Last, First" or "Last, F.M.", names without a comma are organizations.
+fn person(name: &str) -> Person {
+    match name.split_once(',') {
+        Some((family, given)) => {
+            // "F.M." has to be "F. M." to be read as two initials, a suffix like "Jr." is left out.
+            let given = given.split(',').next().unwrap_or("").replace('.', ". ");
+            Person::new(family.trim(), given.split_whitespace().collect::<Vec<&str>>().join(" "))
+        }
+        None => Person::new(name.trim(), ""),
+    }
+}
+
+// PY has the year, DA the full date; both are "YYYY/MM/DD/other" with parts left empty.
+fn date(year: &str, full: &str) -> Option<PublicationDate> {
+    let parse = |text: &str| -> Option<PublicationDate> {
+        let mut parts = text.split('/').map(|part| part.trim().parse::<u32>().ok());
+        let year = parts.next().flatten()?;
+        let month = parts.next().flatten().filter(|month| (1..=12).contains(month));
+        let day = parts.next().flatten().filter(|day| month.is_some() && (1..=31).contains(day));
+        Some(PublicationDate { year: year as i32, month, day })
+    };
+    match (parse(year), parse(full)) {
+        (Some(year), Some(full)) if full.year == year.year => Some(full),
+        (Some(year), _) => Some(year),
+        (None, full) => full,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_and_repeated_tags() {
+        let text = "\u{feff}TY  - JOUR\r\nAU  - Smith, John A.\r\nAU  - Pérez, M.J.\r\nA1  - World Health Organization\r\nTI  - A title\r\nER  - \r\n\r\nTY  - BOOK\r\nTI  - Cut before the end\r\n";
+        let records = parse(text);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].all(&["AU", "A1"]), ["Smith, John A.", "Pérez, M.J.", "World Health Organization"]);
+        assert_eq!(records[1].get(&["TI"]), "Cut before the end");
+
+        let (apa, warnings) = to_apa(&records[0], Lang::English);
+        assert!(warnings.is_empty());
+        assert_eq!(apa.get("authors"), "Smith, J. A., Pérez, M. J., & World Health Organization");
+    }
+
+    #[test]
+    fn wrapped_values_continue_the_tag_above() {
+        let records = parse("TY  - JOUR\nAU  - Smith,\n  John\nTI  - A title that\nwas wrapped\nER  -\n");
+        assert_eq!(records[0].get(&["AU"]), "Smith, John");
+        assert_eq!(records[0].get(&["TI"]), "A title that was wrapped");
+    }
+
+    #[test]
+    fn dates_and_pages() {
+        let records = parse("TY  - NEWS\nPY  - 2020\nDA  - 2020/03/05/\nSP  - 4\nER  -\nTY  - JOUR\nPY  - 2019///\nDA  - 2018/01//\nC7  - e1001\nER  -\n");
+        let (news, _) = to_apa(&records[0], Lang::English);
+        assert_eq!(news.get("date"), "2020, March 5");
+        let (journal, _) = to_apa(&records[1], Lang::English);
+        // DA is from another year, PY wins.
+        assert_eq!(journal.get("date"), "2019");
+        assert_eq!(journal.get("pages"), "Article e1001");
+    }
+
+    #[test]
+    fn unknown_types_are_guessed() {
+        let records = parse("TY  - RPRT\nTI  - A report\nBT  - The book\nER  -\n");
+        let (format, warning) = format_of(&records[0]);
+        assert!(format == ApaFormatType::Chapter);
+        assert!(warning.is_some());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut apa = ApaFormat::new(ApaFormatType::Journal, None);
+        apa.set("authors", "Smith, J. A., & Pérez, M.");
+        apa.set("date", "2020");
+        apa.set("title", "A title");
+        apa.set("journal", "A Journal");
+        apa.set("volume", "12");
+        apa.set("issue", "3");
+        apa.set("pages", "100–110");
+        apa.set("doi", "10.1000/xyz");
+
+        let records = parse(&record(&apa));
+        let (read, warnings) = to_apa(&records[0], Lang::English);
+        assert!(warnings.is_empty());
+        assert_eq!(read.data, apa.data);
+    }
+
+    #[test]
+    fn preprints_round_trip() {
+        let mut apa = ApaFormat::new(ApaFormatType::Preprint, None);
+        apa.set("authors", "Smith, J. A., & Pérez, M.");
+        apa.set("date", "2021");
+        apa.set("title", "A preprint");
+        apa.set("archive", "PsyArXiv");
+        apa.set("doi", "10.31234/osf.io/abcde");
+
+        let records = parse(&record(&apa));
+        let (read, warnings) = to_apa(&records[0], Lang::English);
+        assert!(warnings.is_empty());
+        assert!(read.format == ApaFormatType::Preprint);
+        assert_eq!(read.data, apa.data);
+    }
+}
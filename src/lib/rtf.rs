@@ -0,0 +1,97 @@
+// RTF documents, Word and LibreOffice keep the italics when pasting them.
+
+use std::{fs, io, path::Path};
+
+use crate::ApaFormat;
+use crate::output::in_reference_order;
+use crate::rich::{strip_control, Backend};
+use crate::url::Url;
+
+pub struct Rtf;
+impl Backend for Rtf {
+    fn plain(&self, text: &str) -> String {
+        escape_rtf(text)
+    }
+    fn italic(&self, text: &str) -> String {
+        format!("{{\\i {}}}", escape_rtf(text))
+    }
+    fn link(&self, url: &str) -> String {
+        // Placeholders aren't links.
+        if Url::parse(url).is_err() {
+            return escape_rtf(url);
+        }
+        let url = escape_rtf(url);
+        format!("{{\\field{{\\*\\fldinst{{HYPERLINK \"{}\"}}}}{{\\fldrslt{{{}}}}}}}", url, url)
+    }
+}
+
+// A whole document, one paragraph per reference. APA wants them double
+// spaced with a 0.5 inch (720 twips) hanging indent.
+pub fn document(references: &[ApaFormat]) -> String {
+    let mut output = String::from("{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0 Times New Roman;}}\\f0\\fs24\n");
+    for apa in in_reference_order(references) {
+        output.push_str(&format!("{{\\pard\\li720\\fi-720\\sl480\\slmult1 {}\\par}}\n", apa.reference().render(&Rtf)));
+    }
+    output.push('}');
+    output
+}
+
+pub fn write_file(path: &Path, references: &[ApaFormat]) -> io::Result<()> {
+    fs::write(path, document(references))
+}
+
+pub fn escape_rtf(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in strip_control(text).chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                output.push('\\');
+                output.push(c);
+            }
+            _ if c.is_ascii() => output.push(c),
+            // Everything else as signed 16 bit units, with "?" for readers without unicode.
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    output.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApaFormatType;
+
+    #[test]
+    fn only_web_links_are_fields() {
+        assert_eq!(Rtf.link("https://doi.org/10.1000/xyz"), "{\\field{\\*\\fldinst{HYPERLINK \"https://doi.org/10.1000/xyz\"}}{\\fldrslt{https://doi.org/10.1000/xyz}}}");
+        assert_eq!(Rtf.link("URL"), "URL");
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape_rtf(r"C:\ {x}"), r"C:\\ \{x\}");
+        assert_eq!(escape_rtf("Pérez"), r"P\u233?rez");
+        // Outside the BMP, as a surrogate pair of signed units.
+        assert_eq!(escape_rtf("\u{1D434}"), r"\u-10187?\u-9164?");
+        assert_eq!(escape_rtf("a\u{1b}[3mb"), "a[3mb");
+    }
+
+    #[test]
+    fn hanging_indent_paragraphs() {
+        let mut apa = ApaFormat::new(ApaFormatType::Book, None);
+        apa.set("authors", "Núñez, A.");
+        apa.set("date", "2020");
+        apa.set("title", "{Braced}");
+        apa.set("publisher", "Press");
+        assert_eq!(
+            document(&[apa]),
+            "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0 Times New Roman;}}\\f0\\fs24\n\
+             {\\pard\\li720\\fi-720\\sl480\\slmult1 N\\u250?\\u241?ez, A. (2020). {\\i \\{Braced\\}}. Press.\\par}\n}",
+        );
+    }
+}
@@ -0,0 +1,201 @@
+// Field definitions of every apa format, in the order they are shown and edited.
+
+use crate::doi;
+use crate::url;
+use crate::locator::PageLocator;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    /// Authors, editors... "Last, F. M., & Last, F."
+    PersonList,
+    Date,
+    Url,
+    Doi,
+    Number,
+    PageRange,
+}
+
+#[derive(Debug)]
+pub struct FieldDef {
+    /// Used by the formatter and the importers to find the field.
+    pub id: &'static str,
+    /// Shown in the editor.
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub required: bool,
+    /// Printed when the user hasn't added anything.
+    pub placeholder: &'static str,
+}
+
+impl FieldDef {
+    pub const fn required(id: &'static str, label: &'static str, kind: FieldKind, placeholder: &'static str) -> FieldDef {
+        FieldDef { id, label, kind, required: true, placeholder }
+    }
+    pub const fn optional(id: &'static str, label: &'static str, kind: FieldKind, placeholder: &'static str) -> FieldDef {
+        FieldDef { id, label, kind, required: false, placeholder }
+    }
+
+    // Check the contents of the field, returns a warning to show in the editor.
+    pub fn validate(&self, value: &str) -> Option<String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return if self.required { Some(format!("{} is required", self.label)) } else { None };
+        }
+        match self.kind {
+            FieldKind::Text | FieldKind::PersonList => None,
+            FieldKind::Date => {
+                // A year somewhere, or "no date".
+                let has_year = value.split(|c: char| !c.is_ascii_digit()).any(|part| part.len() == 4);
+                if has_year || value == "n.d." || value == "s.f." {
+                    None
+                } else {
+                    Some("date has no year (use n.d. if there is none)".to_string())
+                }
+            }
+            FieldKind::Url => url::Url::parse(value).err().map(|error| error.to_string()),
+            FieldKind::Doi => doi::Doi::parse(value).err().map(|error| error.to_string()),
+            FieldKind::Number => {
+                if value.chars().all(|c| c.is_ascii_digit()) {
+                    None
+                } else {
+                    Some(format!("{} should be a number", self.label))
+                }
+            }
+            FieldKind::PageRange => {
+                match PageLocator::parse(value) {
+                    Some((_, warnings)) if !warnings.is_empty() => Some(warnings.join(", ")),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+use FieldKind::*;
+
+pub static WEBSITE: [FieldDef; 7] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "date", Date, "date"),
+    FieldDef::required("title", "title", Text, "Title the article"),
+    FieldDef::required("website", "website", Text, "Website"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::required("url", "URL", Url, "URL"),
+    // Pages change, so APA wants the day they were read. Today if it's empty.
+    FieldDef::optional("retrieved", "retrieved", Date, "today"),
+];
+
+pub static NEWSPAPER: [FieldDef; 7] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "date", Date, "date"),
+    FieldDef::required("title", "title", Text, "Title the article"),
+    FieldDef::required("newspaper", "newspaper", Text, "Newspaper"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::required("url", "URL", Url, "URL"),
+    FieldDef::optional("retrieved", "retrieved", Date, "today"),
+];
+
+pub static DICTIONARY: [FieldDef; 9] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "date", Date, "date"),
+    FieldDef::required("word", "word", Text, "Word"),
+    FieldDef::optional("editors", "editors", PersonList, "Editors's Initial(s). Last Name"),
+    FieldDef::required("dictionary", "dictionary", Text, "Dictionary"),
+    FieldDef::optional("publisher", "publisher", Text, "Publisher"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::required("url", "URL", Url, "URL"),
+    FieldDef::optional("retrieved", "retrieved", Date, "today"),
+];
+
+pub static JOURNAL: [FieldDef; 9] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "year", Date, "year"),
+    FieldDef::required("title", "title", Text, "Title of the article"),
+    FieldDef::required("journal", "journal", Text, "Journal"),
+    FieldDef::optional("volume", "volume", Number, "Volume"),
+    FieldDef::optional("issue", "issue", Text, "Issue"),
+    FieldDef::optional("pages", "pages", PageRange, "pages"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::optional("url", "URL", Url, "URL"),
+];
+
+pub static CHAPTER: [FieldDef; 9] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "year", Date, "year"),
+    FieldDef::required("title", "title", Text, "Title of the chapter"),
+    FieldDef::optional("editors", "editors", PersonList, "Editors's Initial(s). Last Name"),
+    FieldDef::required("book", "book", Text, "Title of the book"),
+    FieldDef::optional("pages", "pages", PageRange, "pages"),
+    FieldDef::required("publisher", "publisher", Text, "Publisher"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::optional("url", "URL", Url, "URL"),
+];
+
+pub static BOOK: [FieldDef; 7] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "year", Date, "year"),
+    FieldDef::required("title", "title", Text, "Title of the book"),
+    FieldDef::optional("edition", "edition", Text, "Edition"),
+    FieldDef::required("publisher", "publisher", Text, "Publisher"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::optional("url", "URL", Url, "URL"),
+];
+
+// The URL should be the one of the version that was read, like arxiv.org/abs/2001.00001v2.
+pub static PREPRINT: [FieldDef; 6] = [
+    FieldDef::required("authors", "authors", PersonList, "Author's Last Name, Initial(s)"),
+    FieldDef::required("date", "year", Date, "year"),
+    FieldDef::required("title", "title", Text, "Title of the preprint"),
+    FieldDef::required("archive", "archive", Text, "Archive name"),
+    FieldDef::optional("doi", "DOI", Doi, "DOI"),
+    FieldDef::optional("url", "URL", Url, "URL"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(kind: FieldKind, required: bool) -> FieldDef {
+        FieldDef { id: "field", label: "field", kind, required, placeholder: "" }
+    }
+
+    #[test]
+    fn empty_fields() {
+        assert_eq!(field(Text, true).validate("  "), Some("field is required".to_string()));
+        assert_eq!(field(Url, false).validate(""), None);
+        assert_eq!(field(PersonList, true).validate("Smith, J."), None);
+    }
+
+    #[test]
+    fn dates_need_a_year() {
+        assert_eq!(field(Date, true).validate("2020, May 4"), None);
+        assert_eq!(field(Date, true).validate("n.d."), None);
+        assert_eq!(field(Date, true).validate("s.f."), None);
+        assert_eq!(field(Date, true).validate("May 4"), Some("date has no year (use n.d. if there is none)".to_string()));
+        assert!(field(Date, true).validate("20, May 4").is_some());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(field(Number, false).validate("12"), None);
+        assert_eq!(field(Number, false).validate("12a"), Some("field should be a number".to_string()));
+    }
+
+    #[test]
+    fn links_and_pages() {
+        assert_eq!(field(Url, true).validate("https://example.org"), None);
+        assert!(field(Url, true).validate("not a url").is_some());
+        assert_eq!(field(Doi, false).validate("10.1000/xyz"), None);
+        assert!(field(Doi, false).validate("11.1000/xyz").is_some());
+        assert_eq!(field(PageRange, false).validate("12-34"), None);
+        assert_eq!(field(PageRange, false).validate("34-12"), Some("page range 34–12 is reversed".to_string()));
+    }
+
+    #[test]
+    fn every_format_starts_with_the_authors_and_date() {
+        for fields in [&WEBSITE[..], &NEWSPAPER, &DICTIONARY, &JOURNAL, &CHAPTER, &BOOK, &PREPRINT] {
+            assert_eq!(fields[0].id, "authors");
+            assert_eq!(fields[1].id, "date");
+        }
+    }
+}
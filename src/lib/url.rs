@@ -0,0 +1,210 @@
+// URLs pasted by the user, cleaned up before they go into the reference.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+    pub scheme: String,
+    /// Host with the port, if there is one.
+    pub host: String,
+    /// Path, query and fragment, starting with the slash.
+    pub rest: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UrlError {
+    Empty,
+    MissingScheme,
+    UnsupportedScheme(String),
+    MissingHost,
+    InvalidHost(String),
+    Whitespace,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "URL is empty"),
+            Self::MissingScheme => write!(f, "URL should start with http:// or https://"),
+            Self::UnsupportedScheme(scheme) => write!(f, "\"{}\" links can't be opened by readers", scheme),
+            Self::MissingHost => write!(f, "URL is missing the website"),
+            Self::InvalidHost(host) => write!(f, "\"{}\" is not a valid website", host),
+            Self::Whitespace => write!(f, "URL can't contain spaces"),
+        }
+    }
+}
+
+// Query parameters that only track who clicked the link.
+const TRACKING_PARAMETERS: &[&str] = &["fbclid", "gclid", "mc_eid"];
+
+impl Url {
+    pub fn parse(input: &str) -> Result<Url, UrlError> {
+        // A trailing period would be confused with the one ending the reference.
+        let text = input.trim().trim_end_matches('.');
+        if text.is_empty() {
+            return Err(UrlError::Empty);
+        }
+        if text.contains(char::is_whitespace) {
+            return Err(UrlError::Whitespace);
+        }
+
+        let (scheme, after) = match text.split_once("://") {
+            Some(parts) => parts,
+            None => return Err(UrlError::MissingScheme),
+        };
+        let scheme = scheme.to_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(UrlError::UnsupportedScheme(scheme));
+        }
+
+        // The host ends where the path, query or fragment starts.
+        let end = after.find(['/', '?', '#']).unwrap_or(after.len());
+        let (host, rest) = after.split_at(end);
+        if host.is_empty() {
+            return Err(UrlError::MissingHost);
+        }
+        let host = host.to_lowercase();
+        if !valid_host(&host) {
+            return Err(UrlError::InvalidHost(host));
+        }
+
+        Ok(Url { scheme, host, rest: strip_tracking(rest) })
+    }
+
+    // Where a long URL can be split between lines (byte positions, the line
+    // ends right before them). Breaks go after "//" and before most
+    // punctuation, so the reader doesn't think the URL ends at the break.
+    pub fn break_points(url: &str) -> Vec<usize> {
+        let mut points = Vec::new();
+        let mut chars = url.char_indices();
+        let mut previous = match chars.next() {
+            Some((_, c)) => c,
+            None => return points,
+        };
+        for (i, c) in chars {
+            let after_double_slash = previous == '/' && url[..i].ends_with("//");
+            let before_punctuation = matches!(c, '.' | ',' | '-' | '_' | '~' | '?' | '#' | '%' | '=' | '&')
+                || (c == '/' && previous != '/' && !url[i..].starts_with("//"));
+            let after_joiner = matches!(previous, '=' | '&');
+            if after_double_slash || before_punctuation || after_joiner {
+                points.push(i);
+            }
+            previous = c;
+        }
+        points.dedup();
+        points
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://{}{}", self.scheme, self.host, self.rest)
+    }
+}
+
+fn valid_host(host: &str) -> bool {
+    // Remove the user and the port.
+    let host = host.rsplit('@').next().unwrap_or(host);
+    if host.starts_with('[') {
+        // IPv6
+        return host.contains(']');
+    }
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        Some(_) => return false,
+        None => host,
+    };
+    // "example.org." is the same host, written as fully qualified.
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host == "localhost" {
+        return true;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    labels.len() > 1
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+fn strip_tracking(rest: &str) -> String {
+    // Keep the fragment away from the query.
+    let (before_fragment, fragment) = match rest.find('#') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let (path, query) = match before_fragment.split_once('?') {
+        Some(parts) => parts,
+        None => return rest.to_string(),
+    };
+
+    let kept: Vec<&str> = query.split('&')
+        .filter(|parameter| {
+            let name = parameter.split('=').next().unwrap_or("").to_lowercase();
+            !parameter.is_empty() && !name.starts_with("utm_") && !TRACKING_PARAMETERS.contains(&name.as_str())
+        })
+        .collect();
+
+    if kept.is_empty() {
+        format!("{}{}", path, fragment)
+    } else {
+        format!("{}?{}{}", path, kept.join("&"), fragment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> String {
+        Url::parse(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn hosts() {
+        assert_eq!(parse(" HTTPS://Example.ORG/Path "), "https://example.org/Path");
+        assert_eq!(parse("http://localhost:3000"), "http://localhost:3000");
+        assert_eq!(parse("https://example.org:8080/x"), "https://example.org:8080/x");
+        assert_eq!(parse("https://user@example.org"), "https://user@example.org");
+        assert_eq!(parse("https://[::1]:8080/"), "https://[::1]:8080/");
+        // Sentences end in a period, fully qualified hosts too.
+        assert_eq!(parse("https://example.org."), "https://example.org");
+        assert_eq!(parse("https://example.org./page"), "https://example.org./page");
+        // Internationalized names, as typed or in punycode.
+        assert_eq!(parse("https://Ñandú.cl/aves"), "https://ñandú.cl/aves");
+        assert_eq!(parse("https://xn--and-6ma2c.cl"), "https://xn--and-6ma2c.cl");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Url::parse(" "), Err(UrlError::Empty));
+        assert_eq!(Url::parse("example.org"), Err(UrlError::MissingScheme));
+        assert_eq!(Url::parse("ftp://example.org"), Err(UrlError::UnsupportedScheme("ftp".to_string())));
+        assert_eq!(Url::parse("https:///path"), Err(UrlError::MissingHost));
+        assert_eq!(Url::parse("https://example"), Err(UrlError::InvalidHost("example".to_string())));
+        assert_eq!(Url::parse("https://-example.org"), Err(UrlError::InvalidHost("-example.org".to_string())));
+        assert_eq!(Url::parse("https://example..org"), Err(UrlError::InvalidHost("example..org".to_string())));
+        assert_eq!(Url::parse("https://example.org:80a"), Err(UrlError::InvalidHost("example.org:80a".to_string())));
+        assert_eq!(Url::parse("https://exa mple.org"), Err(UrlError::Whitespace));
+    }
+
+    #[test]
+    fn tracking_parameters() {
+        assert_eq!(parse("https://example.org/a?utm_source=x&id=3&UTM_Medium=y#part"), "https://example.org/a?id=3#part");
+        assert_eq!(parse("https://example.org/a?fbclid=1&gclid=2"), "https://example.org/a");
+        assert_eq!(parse("https://example.org/a?utm_source=x#utm_b"), "https://example.org/a#utm_b");
+        assert_eq!(parse("https://example.org/a?q=utm_source&&page=2"), "https://example.org/a?q=utm_source&page=2");
+        assert_eq!(parse("https://example.org/a#?utm_source=x"), "https://example.org/a#?utm_source=x");
+    }
+
+    #[test]
+    fn break_points() {
+        assert_eq!(Url::break_points(""), Vec::<usize>::new());
+        // After "//", before the dot and the slash, around = and &.
+        assert_eq!(Url::break_points("https://example.org/a-b?x=1&y=2"), [8, 15, 19, 21, 23, 25, 26, 27, 28, 29, 30]);
+        // Byte positions, past multibyte letters.
+        assert_eq!(Url::break_points("https://ñ.cl"), [8, 10]);
+    }
+}
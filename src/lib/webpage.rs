@@ -0,0 +1,420 @@
+// Metadata of a web page: Highwire citation_* tags, OpenGraph, Dublin Core
+// and schema.org JSON-LD, in that order of trust.
+
+use serde_json::Value;
+
+use crate::date::PublicationDate;
+use crate::doi::Doi;
+use crate::fetch::{FetchError, HttpClient};
+use crate::person::{format_people, Person};
+use crate::url::Url;
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// What a page says about itself.
+#[derive(Debug, Default)]
+pub struct PageMetadata {
+    /// <meta> names and properties in lowercase, with their content.
+    pub meta: Vec<(String, String)>,
+    /// JSON-LD blocks that could be read.
+    pub json_ld: Vec<Value>,
+    pub title: String,
+    pub canonical: String,
+}
+
+impl PageMetadata {
+    // Every value of a meta tag, in the order they are in the page.
+    pub fn all(&self, name: &str) -> Vec<&str> {
+        self.meta.iter()
+            .filter(|(key, value)| key == name && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    // The first of the meta tags that is there.
+    pub fn first(&self, names: &[&str]) -> String {
+        names.iter()
+            .find_map(|name| self.all(name).first().map(|value| value.to_string()))
+            .unwrap_or_default()
+    }
+
+    // The Article or WebPage item of the JSON-LD, pages often have more than one.
+    pub fn article(&self) -> Option<&Value> {
+        let mut items = Vec::new();
+        for value in &self.json_ld {
+            collect_items(value, &mut items);
+        }
+        items.iter()
+            .find(|item| types(item).iter().any(|kind| kind.ends_with("Article") || kind == "BlogPosting" || kind == "Report"))
+            .or_else(|| items.iter().find(|item| types(item).iter().any(|kind| kind.ends_with("WebPage"))))
+            .copied()
+    }
+}
+
+// Download the page, the URL is given to it in case it doesn't say its own.
+pub fn lookup(client: &dyn HttpClient, url: &Url, lang: Lang) -> Result<(ApaFormat, Vec<String>), FetchError> {
+    let html = client.get(&url.to_string(), "text/html")?;
+    Ok(from_html(&html, Some(url), lang))
+}
+
+pub fn from_html(html: &str, url: Option<&Url>, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let page = parse(html);
+    let article = page.article();
+    let mut warnings = Vec::new();
+
+    // News sites say so in their JSON-LD.
+    let news = article.is_some_and(|article| types(article).iter().any(|kind| kind.contains("News") || kind == "Reportage"));
+    let format = if news { ApaFormatType::Newspaper } else { ApaFormatType::Website };
+    let mut apa = ApaFormat::new(format, Some(lang));
+
+    // Authors
+    let mut authors: Vec<Person> = page.all("citation_author").into_iter().map(person).collect();
+    if authors.is_empty() {
+        authors = article.map(|article| json_people(article.get("author"))).unwrap_or_default();
+    }
+    if authors.is_empty() {
+        authors = page.all("dc.creator").into_iter()
+            .chain(page.all("author"))
+            .chain(page.all("article:author"))
+            // article:author is often the link to the author's page.
+            .filter(|name| !name.starts_with("http"))
+            .map(person)
+            .collect();
+    }
+    apa.set("authors", format_people(&authors));
+
+    // Date
+    let json_date = article.map(|article| json_text(article.get("datePublished"))).unwrap_or_default();
+    let date = [
+        page.first(&["citation_publication_date", "citation_date", "citation_online_date", "article:published_time"]),
+        json_date,
+        page.first(&["dc.date", "dcterms.date", "dc.date.issued", "dcterms.issued", "date", "pubdate"]),
+    ].into_iter().find(|date| !date.is_empty()).unwrap_or_default();
+    // "2020-05-03T10:00:00Z", the time isn't needed.
+    match PublicationDate::parse(date.split('T').next().unwrap_or("")) {
+        Some(date) => {
+            apa.set_date(&date);
+        }
+        None => warnings.push("the page has no date, use n.d. if there is none".to_string()),
+    }
+
+    // Title
+    let json_title = article.map(|article| json_text(article.get("headline"))).unwrap_or_default();
+    let title = [
+        page.first(&["citation_title", "og:title", "dc.title"]),
+        json_title,
+        page.first(&["twitter:title"]),
+        page.title.clone(),
+    ].into_iter().find(|title| !title.is_empty()).unwrap_or_default();
+    apa.set("title", title);
+
+    // Site name, the host when the page doesn't have one.
+    let publisher = article.and_then(|article| article.get("publisher")).map(|publisher| json_text(publisher.get("name"))).unwrap_or_default();
+    let site = [
+        page.first(&["og:site_name", "citation_journal_title", "dc.publisher", "citation_publisher"]),
+        publisher,
+        page.first(&["application-name"]),
+    ].into_iter().find(|site| !site.is_empty());
+    let page_url = [page.canonical.clone(), page.first(&["og:url", "citation_public_url", "citation_abstract_html_url"])]
+        .into_iter()
+        .find_map(|url| Url::parse(&url).ok())
+        .or_else(|| url.cloned());
+    let site = site.or_else(|| page_url.as_ref().map(|url| url.host.trim_start_matches("www.").to_string()));
+    if let Some(id) = format.container() {
+        apa.set(id, site.unwrap_or_default());
+    }
+
+    // Links
+    let doi = page.first(&["citation_doi", "dc.identifier", "prism.doi"]);
+    if Doi::parse(&doi).is_ok() {
+        apa.set("doi", doi);
+    }
+    match page_url {
+        Some(url) => {
+            apa.set("url", url.to_string());
+        }
+        None => warnings.push("the page doesn't say its URL, add it".to_string()),
+    }
+
+    (apa, warnings)
+}
+
+// "Last, First" or "First Last".
+fn person(name: &str) -> Person {
+    match name.split_once(',') {
+        Some((family, given)) => Person::new(family.trim(), given.trim()),
+        None => Person::from_full_name(name),
+    }
+}
+
+// A name, a Person or Organization item, or a list of them.
+fn json_people(value: Option<&Value>) -> Vec<Person> {
+    match value {
+        Some(Value::Array(people)) => people.iter().flat_map(|person| json_people(Some(person))).collect(),
+        Some(Value::String(name)) => vec![person(name)],
+        Some(item @ Value::Object(_)) => {
+            let name = json_text(item.get("name"));
+            if name.is_empty() {
+                Vec::new()
+            } else if types(item).iter().any(|kind| kind.ends_with("Organization")) {
+                vec![Person::new(name, "")]
+            } else {
+                vec![person(&name)]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn json_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => decode_entities(text.trim()),
+        Some(Value::Array(values)) => json_text(values.first()),
+        _ => String::new(),
+    }
+}
+
+// "@type" can be a string or a list.
+fn types(item: &Value) -> Vec<String> {
+    match item.get("@type") {
+        Some(Value::String(kind)) => vec![kind.clone()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Items can be nested in lists and in @graph.
+fn collect_items<'a>(value: &'a Value, items: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|value| collect_items(value, items)),
+        Value::Object(object) => {
+            items.push(value);
+            if let Some(graph) = object.get("@graph") {
+                collect_items(graph, items);
+            }
+        }
+        _ => {}
+    }
+}
+
+/* HTML */
+
+// Only <title>, <meta>, <link rel="canonical"> and the JSON-LD scripts are read.
+pub fn parse(html: &str) -> PageMetadata {
+    let lowercase = html.to_ascii_lowercase();
+    let mut page = PageMetadata::default();
+    let mut pos = 0;
+
+    while let Some(start) = lowercase[pos..].find('<').map(|start| start + pos) {
+        if lowercase[start..].starts_with("<!--") {
+            pos = lowercase[start..].find("-->").map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+        let name_end = lowercase[start + 1..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .map_or(html.len(), |end| start + 1 + end);
+        let name = &lowercase[start + 1..name_end];
+        // "a < b" and closing tags, only what starts with a tag name is read.
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            pos = start + 1;
+            continue;
+        }
+        let end = tag_end(html, name_end);
+        let attributes = attributes(&html[name_end..end]);
+        pos = (end + 1).min(html.len());
+
+        let attribute = |key: &str| {
+            attributes.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str()).unwrap_or("")
+        };
+        match name {
+            "meta" => {
+                let key = [attribute("property"), attribute("name"), attribute("itemprop")]
+                    .into_iter()
+                    .find(|key| !key.is_empty());
+                if let Some(key) = key {
+                    page.meta.push((key.to_lowercase(), attribute("content").trim().to_string()));
+                }
+            }
+            "link" if attribute("rel").eq_ignore_ascii_case("canonical") => page.canonical = attribute("href").to_string(),
+            "title" if page.title.is_empty() => {
+                let close = lowercase[pos..].find("</title").map_or(html.len(), |close| pos + close);
+                page.title = decode_entities(html[pos..close].trim());
+                pos = close;
+            }
+            // Scripts can have < in them, they are skipped as a whole.
+            "script" | "style" => {
+                let close = lowercase[pos..].find(&format!("</{}", name)).map_or(html.len(), |close| pos + close);
+                if attribute("type").eq_ignore_ascii_case("application/ld+json") {
+                    if let Ok(value) = serde_json::from_str(html[pos..close].trim()) {
+                        page.json_ld.push(value);
+                    }
+                }
+                pos = close;
+            }
+            _ => {}
+        }
+    }
+    page
+}
+
+// Where the tag closes, > inside quoted attributes doesn't count.
+fn tag_end(html: &str, from: usize) -> usize {
+    let mut quote = None;
+    let mut after_equals = false;
+    for (i, c) in html[from..].char_indices() {
+        match (c, quote) {
+            (c, Some(open)) => {
+                if c == open {
+                    quote = None;
+                }
+                continue;
+            }
+            // Only values are quoted, an apostrophe anywhere else is text.
+            ('"' | '\'', None) if after_equals => {
+                quote = Some(c);
+                continue;
+            }
+            ('>', None) => return from + i,
+            _ => {}
+        }
+        after_equals = c == '=' || (after_equals && c.is_whitespace());
+    }
+    html.len()
+}
+
+// name="value", name='value', name=value or just name.
+fn attributes(text: &str) -> Vec<(String, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() || chars[i] == '/' {
+            i += 1;
+            continue;
+        }
+        let name_start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '=' | '/') {
+            i += 1;
+        }
+        let name: String = chars[name_start..i].iter().collect::<String>().to_lowercase();
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if chars.get(i) == Some(&'=') {
+            i += 1;
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            match chars.get(i) {
+                Some(&quote @ ('"' | '\'')) => {
+                    i += 1;
+                    while i < chars.len() && chars[i] != quote {
+                        value.push(chars[i]);
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                _ => {
+                    while i < chars.len() && !chars[i].is_whitespace() {
+                        value.push(chars[i]);
+                        i += 1;
+                    }
+                }
+            }
+        }
+        if !name.is_empty() {
+            attributes.push((name, decode_entities(&value)));
+        }
+    }
+    attributes
+}
+
+// &amp;, &quot;, &#39;, &#x2019;... back to their characters.
+pub fn decode_entities(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(';').filter(|end| *end <= 10);
+        let decoded = end.and_then(|end| {
+            let entity = &rest[1..end];
+            match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                "ndash" => Some('–'),
+                "mdash" => Some('—'),
+                "rsquo" => Some('’'),
+                "lsquo" => Some('‘'),
+                "hellip" => Some('…'),
+                _ => {
+                    let number = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()));
+                    number.and_then(char::from_u32)
+                }
+            }
+        });
+        match (decoded, end) {
+            (Some(c), Some(end)) => {
+                output.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = include_str!("../../tests/fixtures/webpage/article.html");
+
+    #[test]
+    fn saved_page() {
+        let (apa, warnings) = from_html(ARTICLE, None, Lang::English);
+        assert!(warnings.is_empty());
+        assert!(apa.format == ApaFormatType::Website);
+        // citation_author over the JSON-LD authors.
+        assert_eq!(apa.get("authors"), "Pérez, M. J., & van der Berg, J.");
+        // The JSON-LD date, without the time.
+        assert_eq!(apa.get("date"), "2021, May 3");
+        // og:title over the JSON-LD headline and the <title>.
+        assert_eq!(apa.get("title"), "Why comparisons matter & how to read them");
+        // og:site_name is after the text with < and an apostrophe.
+        assert_eq!(apa.get("website"), "The Example Blog");
+        // The canonical link, without the tracking parameters.
+        assert_eq!(apa.get("url"), "https://blog.example.org/2021/05/comparisons");
+    }
+
+    #[test]
+    fn stray_brackets_and_apostrophes_are_text() {
+        let page = parse("<p>a < b, it's</p><meta name=\"author\" content=\"A. Person\"><p>x <y, don't</p><meta property='og:title' content='It\"s > fine'>");
+        assert_eq!(page.first(&["author"]), "A. Person");
+        assert_eq!(page.first(&["og:title"]), "It\"s > fine");
+    }
+
+    #[test]
+    fn comments_and_scripts_are_skipped() {
+        let page = parse(ARTICLE);
+        assert_eq!(page.title, "Why comparisons matter | The Example Blog");
+        assert!(page.all("citation_title").is_empty());
+        assert_eq!(page.json_ld.len(), 1);
+        assert_eq!(page.article().map(|article| json_text(article.get("headline"))).as_deref(), Some("Comparisons, the JSON-LD title"));
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry&#8217;s &#x2014; &unknown; & more"), "Tom & Jerry’s — &unknown; & more");
+    }
+}
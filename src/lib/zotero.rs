@@ -0,0 +1,338 @@
+// The items of a local Zotero library, read from its zotero.sqlite.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, process};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::date::PublicationDate;
+use crate::import::Candidate;
+use crate::person::{format_book_editors, format_editors, format_people, Person};
+use crate::{ApaFormat, ApaFormatType, Lang};
+
+// An item with its fields by Zotero name ("title", "publicationTitle"...).
+#[derive(Debug, Default)]
+pub struct ZoteroItem {
+    pub key: String,
+    pub item_type: String,
+    pub fields: HashMap<String, String>,
+    /// The creator type ("author", "editor"...) and the person, in order.
+    pub creators: Vec<(String, Person)>,
+}
+
+impl ZoteroItem {
+    pub fn get(&self, field: &str) -> &str {
+        self.fields.get(field).map_or("", |value| value.trim())
+    }
+
+    // The first of the fields that has something.
+    fn first(&self, fields: &[&str]) -> &str {
+        fields.iter().map(|field| self.get(field)).find(|value| !value.is_empty()).unwrap_or("")
+    }
+
+    fn people(&self, creator_type: &str) -> Vec<Person> {
+        self.creators.iter()
+            .filter(|(kind, _)| kind == creator_type)
+            .map(|(_, person)| person.clone())
+            .collect()
+    }
+
+    // What the search looks in.
+    fn matches(&self, words: &[String]) -> bool {
+        let mut text = self.fields.values().cloned().collect::<Vec<String>>().join(" ");
+        for (_, person) in &self.creators {
+            text.push(' ');
+            text.push_str(&person.apa());
+        }
+        let text = text.to_lowercase();
+        words.iter().all(|word| text.contains(word.as_str()))
+    }
+}
+
+// APA_ZOTERO_DB, or where Zotero keeps it by default.
+pub fn default_path() -> PathBuf {
+    if let Ok(path) = env::var("APA_ZOTERO_DB") {
+        return PathBuf::from(path);
+    }
+    let home = env::var("HOME").or_else(|_| env::var("USERPROFILE")).unwrap_or_default();
+    Path::new(&home).join("Zotero").join("zotero.sqlite")
+}
+
+// Zotero keeps the database locked and changes it while it runs, a copy is
+// read instead. The copy is removed when this is dropped.
+struct Snapshot {
+    path: PathBuf,
+}
+
+impl Snapshot {
+    fn copy(path: &Path) -> Result<Snapshot, String> {
+        if !path.is_file() {
+            return Err(format!("There is no Zotero database at {}, set APA_ZOTERO_DB", path.display()));
+        }
+        static COPIES: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("apa-zotero-{}-{}.sqlite", process::id(), COPIES.fetch_add(1, Ordering::Relaxed));
+        let copy = env::temp_dir().join(name);
+        fs::copy(path, &copy).map_err(|error| format!("Couldn't copy {}: {}", path.display(), error))?;
+        Ok(Snapshot { path: copy })
+    }
+
+    // Nothing else uses the copy, so it can be read as immutable, without locks.
+    fn open(&self) -> Result<Connection, String> {
+        let uri = format!("file:{}?immutable=1", uri_path(&self.path));
+        Connection::open_with_flags(uri, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI)
+            .map_err(|error| format!("Couldn't open {}: {}", self.path.display(), error))
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// The path in a file: URI, where ?, # and % have a meaning of their own.
+fn uri_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut output = String::with_capacity(path.len());
+    // "C:/Users" has to be "/C:/Users".
+    if !path.starts_with('/') {
+        output.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => output.push(byte as char),
+            _ => output.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    output
+}
+
+// Every item with all the words of the query, the most recently changed first.
+pub fn search(path: &Path, query: &str, lang: Lang) -> Result<Vec<Candidate>, String> {
+    let snapshot = Snapshot::copy(path)?;
+    let items = read_items(&snapshot.open()?).map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let candidates: Vec<Candidate> = items.iter()
+        .filter(|item| item.matches(&words))
+        .map(|item| {
+            let (apa, warnings) = to_apa(item, lang);
+            Candidate::new(&item.key, apa, warnings)
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(format!("Nothing in the Zotero library matches \"{}\"", query));
+    }
+    Ok(candidates)
+}
+
+pub fn read_items(connection: &Connection) -> rusqlite::Result<Vec<ZoteroItem>> {
+    // Notes, attachments and the trash aren't references.
+    let mut statement = connection.prepare(
+        "SELECT items.itemID, items.key, itemTypes.typeName FROM items
+         JOIN itemTypes ON itemTypes.itemTypeID = items.itemTypeID
+         WHERE itemTypes.typeName NOT IN ('attachment', 'note', 'annotation')
+           AND items.itemID NOT IN (SELECT itemID FROM deletedItems)
+         ORDER BY items.dateModified DESC",
+    )?;
+    let mut items: Vec<(i64, ZoteroItem)> = statement
+        .query_map([], |row| {
+            Ok((row.get(0)?, ZoteroItem { key: row.get(1)?, item_type: row.get(2)?, ..Default::default() }))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    let index: HashMap<i64, usize> = items.iter().enumerate().map(|(i, (id, _))| (*id, i)).collect();
+
+    let mut statement = connection.prepare(
+        "SELECT itemData.itemID, fields.fieldName, itemDataValues.value FROM itemData
+         JOIN fields ON fields.fieldID = itemData.fieldID
+         JOIN itemDataValues ON itemDataValues.valueID = itemData.valueID",
+    )?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(&i) = index.get(&row.get::<_, i64>(0)?) {
+            // Values can be numbers too.
+            let value = match row.get_ref(2)? {
+                rusqlite::types::ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+                rusqlite::types::ValueRef::Integer(number) => number.to_string(),
+                rusqlite::types::ValueRef::Real(number) => number.to_string(),
+                _ => continue,
+            };
+            items[i].1.fields.insert(row.get(1)?, value);
+        }
+    }
+
+    // fieldMode 1 is a single name, like an organization.
+    let mut statement = connection.prepare(
+        "SELECT itemCreators.itemID, creatorTypes.creatorType, creators.lastName, creators.firstName, creators.fieldMode
+         FROM itemCreators
+         JOIN creators ON creators.creatorID = itemCreators.creatorID
+         JOIN creatorTypes ON creatorTypes.creatorTypeID = itemCreators.creatorTypeID
+         ORDER BY itemCreators.itemID, itemCreators.orderIndex",
+    )?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        if let Some(&i) = index.get(&row.get::<_, i64>(0)?) {
+            let family: String = row.get::<_, Option<String>>(2)?.unwrap_or_default();
+            let given: String = row.get::<_, Option<String>>(3)?.unwrap_or_default();
+            let single = row.get::<_, Option<i64>>(4)?.unwrap_or(0) == 1;
+            let person = if single { Person::new(family, "") } else { Person::new(family, given) };
+            items[i].1.creators.push((row.get(1)?, person));
+        }
+    }
+
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+// Which format a Zotero item type is, and a warning when it's only the closest one.
+pub fn format_of(item: &ZoteroItem) -> (ApaFormatType, Option<String>) {
+    let format = match item.item_type.as_str() {
+        "journalArticle" => ApaFormatType::Journal,
+        "newspaperArticle" | "magazineArticle" => ApaFormatType::Newspaper,
+        "webpage" | "blogPost" | "forumPost" => ApaFormatType::Website,
+        "dictionaryEntry" | "encyclopediaArticle" => ApaFormatType::Dictionary,
+        "bookSection" | "conferencePaper" => ApaFormatType::Chapter,
+        "book" => ApaFormatType::Book,
+        "preprint" => ApaFormatType::Preprint,
+        _ => {
+            let format = if !item.get("publicationTitle").is_empty() {
+                ApaFormatType::Journal
+            } else if !item.get("url").is_empty() {
+                ApaFormatType::Website
+            } else {
+                ApaFormatType::Book
+            };
+            return (format, Some(format!("Zotero type \"{}\" opened as a {}, check the fields", item.item_type, format)));
+        }
+    };
+    (format, None)
+}
+
+pub fn to_apa(item: &ZoteroItem, lang: Lang) -> (ApaFormat, Vec<String>) {
+    let (format, warning) = format_of(item);
+    let mut apa = ApaFormat::new(format, Some(lang));
+    let warnings: Vec<String> = warning.into_iter().collect();
+
+    let authors = item.people("author");
+    let editors = item.people("editor");
+    if authors.is_empty() && !editors.is_empty() && format == ApaFormatType::Book {
+        // Edited books go by their editors.
+        apa.set("authors", format_book_editors(&editors));
+    } else {
+        apa.set("authors", format_people(&authors));
+    }
+    apa.set("editors", format_editors(&editors));
+
+    if let Some(date) = read_date(item.get("date")) {
+        apa.set_date(&date);
+    }
+    if let Some(date) = read_date(item.get("accessDate")) {
+        apa.set("retrieved", date.apa(&lang));
+    }
+
+    if !apa.set("title", item.get("title")) {
+        apa.set("word", item.get("title"));
+    }
+    // Each item type names its container differently.
+    let container = item.first(&[
+        "publicationTitle", "websiteTitle", "blogTitle", "forumTitle", "bookTitle",
+        "proceedingsTitle", "dictionaryTitle", "encyclopediaTitle", "repository",
+    ]);
+    if let Some(id) = format.container() {
+        apa.set(id, container);
+    }
+
+    apa.set("volume", item.get("volume"));
+    apa.set("issue", item.get("issue"));
+    apa.set("pages", item.get("pages"));
+    apa.set("edition", item.get("edition"));
+    apa.set("publisher", item.first(&["publisher", "university", "institution"]));
+    apa.set("doi", item.get("DOI"));
+    apa.set("url", item.get("url"));
+
+    (apa, warnings)
+}
+
+// Zotero stores "2020-03-00 March 2020", the SQL date first with zeros for what's unknown.
+fn read_date(value: &str) -> Option<PublicationDate> {
+    let numbers: Vec<u32> = value.get(..10)
+        .map(|sql| sql.split('-').filter_map(|number| number.parse().ok()).collect())
+        .unwrap_or_default();
+    match numbers.as_slice() {
+        [year, month, day] if *year != 0 => Some(PublicationDate {
+            year: *year as i32,
+            month: (*month != 0).then_some(*month),
+            day: (*day != 0).then_some(*day),
+        }),
+        // Dates Zotero couldn't read keep only the text.
+        _ => PublicationDate::parse(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The tables and columns that are read, with a journal article and a note.
+    fn library(path: &Path) {
+        let connection = Connection::open(path).unwrap();
+        connection.execute_batch(
+            "CREATE TABLE itemTypes (itemTypeID INTEGER PRIMARY KEY, typeName TEXT);
+             CREATE TABLE items (itemID INTEGER PRIMARY KEY, itemTypeID INT, dateModified TEXT, key TEXT);
+             CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY);
+             CREATE TABLE fields (fieldID INTEGER PRIMARY KEY, fieldName TEXT);
+             CREATE TABLE itemDataValues (valueID INTEGER PRIMARY KEY, value);
+             CREATE TABLE itemData (itemID INT, fieldID INT, valueID INT);
+             CREATE TABLE creatorTypes (creatorTypeID INTEGER PRIMARY KEY, creatorType TEXT);
+             CREATE TABLE creators (creatorID INTEGER PRIMARY KEY, firstName TEXT, lastName TEXT, fieldMode INT);
+             CREATE TABLE itemCreators (itemID INT, creatorID INT, creatorTypeID INT, orderIndex INT);
+             INSERT INTO itemTypes VALUES (1, 'journalArticle'), (2, 'note');
+             INSERT INTO items VALUES (1, 1, '2024-01-01', 'ABCD1234'), (2, 2, '2024-01-02', 'NOTE0001');
+             INSERT INTO fields VALUES (1, 'title'), (2, 'publicationTitle'), (3, 'date'), (4, 'volume');
+             INSERT INTO itemDataValues VALUES (1, 'Self-efficacy'), (2, 'Psychological Review'), (3, '1977-00-00 1977'), (4, 84);
+             INSERT INTO itemData VALUES (1, 1, 1), (1, 2, 2), (1, 3, 3), (1, 4, 4);
+             INSERT INTO creatorTypes VALUES (1, 'author');
+             INSERT INTO creators VALUES (1, 'Albert', 'Bandura', 0), (2, NULL, 'Stanford University', 1);
+             INSERT INTO itemCreators VALUES (1, 1, 1, 0), (1, 2, 1, 1);",
+        ).unwrap();
+    }
+
+    #[test]
+    fn paths_with_uri_characters() {
+        let dir = env::temp_dir().join(format!("apa zotero ?#% {}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("zotero.sqlite");
+        library(&path);
+
+        let candidates = search(&path, "bandura efficacy", Lang::English);
+        fs::remove_dir_all(&dir).unwrap();
+        let candidates = candidates.unwrap();
+        assert_eq!(candidates.len(), 1);
+        let apa = &candidates[0].apa;
+        assert_eq!(apa.get("authors"), "Bandura, A., & Stanford University");
+        assert_eq!(apa.get("date"), "1977");
+        assert_eq!(apa.get("journal"), "Psychological Review");
+        assert_eq!(apa.get("volume"), "84");
+    }
+
+    #[test]
+    fn edited_books_go_by_their_editors() {
+        let mut item = ZoteroItem { item_type: "book".to_string(), ..Default::default() };
+        item.creators.push(("editor".to_string(), Person::new("Ericsson", "K. Anders")));
+        let (apa, warnings) = to_apa(&item, Lang::English);
+        assert!(warnings.is_empty());
+        assert_eq!(apa.get("authors"), "Ericsson, K. A. (Ed.)");
+    }
+
+    #[test]
+    fn missing_databases() {
+        assert!(search(Path::new("/nonexistent/zotero.sqlite"), "", Lang::English).is_err());
+    }
+
+    #[test]
+    fn uri_paths_are_encoded() {
+        assert_eq!(uri_path(Path::new("/home/a b/100%?#/zotero.sqlite")), "/home/a%20b/100%25%3F%23/zotero.sqlite");
+    }
+}
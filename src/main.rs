@@ -1,20 +1,30 @@
-pub mod lib;
+pub mod cli;
 pub mod renderer;
 
 
-use lib::{Logic, ApaFormatType, ApaFormat, save_to_x11_clipboard, LogicState, Lang};
+use apa::{Logic, ApaFormatType, ApaFormat, save_to_x11_clipboard, LogicState, Lang, PromptKind, import, output::OutputFormat};
+use apa::fetch::{Endpoints, UreqClient};
 use renderer::render;
 use unicode_segmentation::UnicodeSegmentation;
 use x11_clipboard::Clipboard;
 
-use std::{time, thread, io::stdout, fmt::write, io::Write, process::{Command, Stdio, self}};
+use std::{io::stdout, io::Write, path::Path, process, slice};
 use termion::{input::TermRead, event::Key, raw::IntoRawMode, cursor::DetectCursorPos, terminal_size};
 
 
 fn main() {
+    // Commands don't need the terminal or the clipboard.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        process::exit(cli::run(&args));
+    }
+
     // Define container that houses all of the variables
     let mut logic = Logic::new();
     let clipboard = Clipboard::new().unwrap();
+    // Where the DOI... lookups go.
+    let client = UreqClient::default();
+    let endpoints = Endpoints::from_env();
 
 
     // Terminal
@@ -41,19 +51,46 @@ fn main() {
     for key in stdin.keys() {
 
         if logic.state != LogicState::Result {
+            /* Universal Keys */
+            // Quit to final result key.
+            if let Key::Ctrl('c') = key.as_ref().unwrap() {
+                // Quick fix to exit the program with the cursor at the bottom.
+                logic.state = LogicState::Result;
+                // The selected output format, html by default.
+                logic.selected = 0;
+                logic.message = String::new();
+                render(&logic, &mut stdout, cursor_pos);
+
+                // Copy the apa to the clipboard x11;
+                save_to_x11_clipboard(&clipboard,&logic.apa, OutputFormat::list()[logic.selected]);
+            }
+        } else if matches!(key.as_ref().unwrap(), Key::Left | Key::Right) {
+            /* Output format selection */
+            let output_num: usize = OutputFormat::list().len();
             match key.as_ref().unwrap() {
-                /* Universal Keys */
-                    // Quit to final result key.
-                    Key::Ctrl('c') => {
-                        // Quick fix to exit the program with the cursor at the bottom.
-                        logic.state = LogicState::Result;
-                        render(&logic, &mut stdout, cursor_pos);
-        
-                        // Copy the apa to the clipboard x11;
-                        save_to_x11_clipboard(&clipboard,&logic.apa);
-                    }
-                    _ => {}
-                }
+                Key::Left if logic.selected != 0 => logic.selected -= 1,
+                Key::Right if logic.selected < output_num - 1 => logic.selected += 1,
+                _ => {}
+            }
+
+            // Copy it again in the new format, documents can only be saved.
+            let output = OutputFormat::list()[logic.selected];
+            if output.is_binary() {
+                logic.message = format!("Press (s) to save it as a .{} file", output.extension());
+            } else {
+                logic.message = String::new();
+                save_to_x11_clipboard(&clipboard,&logic.apa, output);
+            }
+            render(&logic, &mut stdout, cursor_pos);
+        } else if let Key::Char('s') = key.as_ref().unwrap() {
+            /* Save the reference to a file in the selected format */
+            let output = OutputFormat::list()[logic.selected];
+            let path = format!("reference.{}", output.extension());
+            logic.message = match output.save(Path::new(&path), slice::from_ref(&logic.apa)) {
+                Ok(()) => format!("Saved to {}", path),
+                Err(error) => format!("Couldn't save {}: {}", path, error),
+            };
+            render(&logic, &mut stdout, cursor_pos);
         } else { /* SUCCESSFULLY LEAVE THE PROGRAM */
             // Leave on any key when in the Result Screen.
             //println!("\nLEAVING");
@@ -74,7 +111,7 @@ fn main() {
             Key::Char('\t') => {
                 logic.edit_state = !logic.edit_state;
                 // Set the cursor position.
-                logic.cursor_pos = logic.apa.data.get(&logic.selected).unwrap().1.len();
+                logic.cursor_pos = logic.apa.data[logic.selected].graphemes(true).count();
             }
 
             /* Selecting Field State */
@@ -89,8 +126,9 @@ fn main() {
             }
             Key::Char('d') if !logic.edit_state => {
                 // Completely wipe the selected field.
-                let apa_field = &mut logic.apa.data.get_mut(&logic.selected).unwrap();
-                apa_field.1 = "".to_string();
+                let apa_field = &mut logic.apa.data[logic.selected];
+                *apa_field = "".to_string();
+                logic.reviewed();
             }
 
         /* Editing State */
@@ -99,9 +137,9 @@ fn main() {
                 logic.cursor_pos -= 1;
             }
             Key::Right if logic.edit_state => {
-                // Get the field's length and check if it's bigger.
-                let apa_field = logic.apa.data.get(&logic.selected).unwrap();
-                if logic.cursor_pos < apa_field.1.len() {
+                // Get the field's length in graphemes and check if it's bigger.
+                let apa_field = &logic.apa.data[logic.selected];
+                if logic.cursor_pos < apa_field.graphemes(true).count() {
                     logic.cursor_pos += 1;
                 }
             }
@@ -110,7 +148,7 @@ fn main() {
                 logic.selected += 1;
 
                 // If current position is too large, switch to field's length
-                let new_field_length = logic.apa.data.get(&logic.selected).unwrap().1.len();
+                let new_field_length = logic.apa.data[logic.selected].graphemes(true).count();
                 if logic.cursor_pos > new_field_length {
                     logic.cursor_pos = new_field_length;
                 }
@@ -120,7 +158,7 @@ fn main() {
                 logic.selected -= 1;
 
                 // If current position is too large, switch to field's length
-                let new_field_length = logic.apa.data.get(&logic.selected).unwrap().1.len();
+                let new_field_length = logic.apa.data[logic.selected].graphemes(true).count();
                 if logic.cursor_pos > new_field_length {
                     logic.cursor_pos = new_field_length;
                 }
@@ -129,31 +167,32 @@ fn main() {
             Key::Backspace if logic.edit_state && logic.cursor_pos != 0 => {
                 // Prevent deleting nothing ^
                 // Delete the last char from the string
-                let apa_field = &mut logic.apa.data.get_mut(&logic.selected).unwrap();
+                let apa_field = &mut logic.apa.data[logic.selected];
                 
                 // Firstly, divide the apa field to graphene_clusters
-                let mut graphene_fields = UnicodeSegmentation::graphemes(apa_field.1.as_str(), true).collect::<Vec<&str>>();
+                let mut graphene_fields = UnicodeSegmentation::graphemes(apa_field.as_str(), true).collect::<Vec<&str>>();
                 // Remove the character found before the cursor.
                 graphene_fields.remove(logic.cursor_pos - 1);
                 
                 // Update the apa field with the remaining clusters.
-                apa_field.1 = graphene_fields.iter()
+                *apa_field = graphene_fields.iter()
                     .map(|cluster| {cluster.to_string()})
                     .collect();
 
 
                 // Update the character position.
                 logic.cursor_pos -= 1;
+                logic.reviewed();
 
             }
             Key::Char(c) if logic.edit_state => {
                 if c == &'\n' {continue};
 
                 // Append the character to the end of the field
-                let apa_field = &mut logic.apa.data.get_mut(&logic.selected).unwrap();
+                let apa_field = &mut logic.apa.data[logic.selected];
 
                 // Divde the apa format to graphene fields
-                let mut graphene_fields = UnicodeSegmentation::graphemes(apa_field.1.as_str(), true).collect::<Vec<&str>>();
+                let mut graphene_fields = UnicodeSegmentation::graphemes(apa_field.as_str(), true).collect::<Vec<&str>>();
                 
                 // Define the character we will insert into a &str
                 let character: &str = &c.to_string();
@@ -163,20 +202,39 @@ fn main() {
                 let new_cursor_pos = graphene_fields.len();
 
                 // Update the field with the graphene_fields turned into a String from a Vec<&str>.
-                apa_field.1 = graphene_fields.iter()
+                *apa_field = graphene_fields.iter()
                     .map(|cluster| {cluster.to_string()})
                     .collect();
 
                 // Update the character position.
                 logic.cursor_pos = new_cursor_pos;
+                logic.reviewed();
             }
             
             _ => {}
         };
         }
 
+    /* Prompt for a path or an identifier, and picking one of the imported references */
+        else if matches!(logic.state, LogicState::Prompt(_) | LogicState::Picking) {
+            // Lookups can take a while.
+            if let (LogicState::Prompt(kind), Key::Char('\n')) = (&logic.state, key.as_ref().unwrap()) {
+                if !matches!(kind, PromptKind::OpenFile | PromptKind::Citation | PromptKind::Zotero) {
+                    logic.message = "Fetching...".to_string();
+                    render(&logic, &mut stdout, cursor_pos);
+                }
+            }
+            let lang = logic.apa.lang;
+            let changed = logic.import_key(key.as_ref().unwrap(), |kind, input| {
+                import::prompt(kind, input, lang, &client, &endpoints)
+            });
+            if changed {
+                write!(stdout, "{}{}", termion::cursor::Goto(1, cursor_pos.1),termion::clear::AfterCursor).unwrap();
+            }
+        }
+
     /* APA selecting mode */
-        if logic.state == LogicState::SelectingFormat {
+        else if logic.state == LogicState::SelectingFormat {
             let format_num: usize = ApaFormatType::list().len();
 
             match key.as_ref().unwrap() {
@@ -190,12 +248,29 @@ fn main() {
                 // Select the format and switch to editing mode
                 Key::Char('\n') => {
                     logic.apa = ApaFormat::new(ApaFormatType::list()[logic.selected], Some(logic.apa.lang));
+                    logic.review.clear();
                     logic.selected = 0;
                     logic.state = LogicState::EditState;
                     // Clear the screen.
                     write!(stdout, "{}{}", termion::cursor::Goto(1, cursor_pos.1),termion::clear::AfterCursor).unwrap();
                 }
 
+                // Fill the reference from a file or a lookup.
+                Key::Char(c @ ('o' | 'd' | 'i' | 'm' | 'a' | 'u' | 'p' | 'z')) => {
+                    logic.prompt = String::new();
+                    logic.message = String::new();
+                    logic.state = LogicState::Prompt(match c {
+                        'o' => PromptKind::OpenFile,
+                        'd' => PromptKind::Doi,
+                        'i' => PromptKind::Isbn,
+                        'm' => PromptKind::Pubmed,
+                        'a' => PromptKind::Preprint,
+                        'u' => PromptKind::Url,
+                        'p' => PromptKind::Citation,
+                        _ => PromptKind::Zotero,
+                    });
+                }
+
                 // Switch language of the default apa format None.
                 Key::Char('\t') => {
                     match logic.apa.lang {
@@ -1,9 +1,9 @@
-use std::{io::{stdout, Write, Stdout}, str::Bytes, ops::Range};
+use std::io::{Write, Stdout};
 
-use crate::lib::{Logic, ApaFormatType, LogicState};
+use apa::{Logic, ApaFormatType, LogicState, rich::Ansi, output::OutputFormat};
 
 
-use termion::{self, raw::{IntoRawMode, RawTerminal}, event::Key, input::TermRead, color::Fg, terminal_size, cursor::DetectCursorPos};
+use termion::{self, raw::RawTerminal, color::Fg, terminal_size, cursor::DetectCursorPos};
 use termion::cursor::Goto;
 use termion::style;
 use termion::color;
@@ -11,14 +11,13 @@ use termion::color;
 pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16, u16)) {
 
     // Dynamically placed cursor. Affects the Goto of all printing.
-    let mut cursor_pos = stdout.cursor_pos().unwrap();
+    let cursor_pos = stdout.cursor_pos().unwrap();
 
     // Check if there's enough space for the program below.
     const PRINT_SIZE: u16 = 8;
     if cursor_pos.1 + PRINT_SIZE >= terminal_size().unwrap().1 {
         // There is not enough space, so we scroll up.
         write!(stdout, "{}", termion::scroll::Up(PRINT_SIZE)).unwrap();
-        cursor_pos.1 -= PRINT_SIZE;
     }
 
     // Select format
@@ -56,6 +55,11 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
             ).unwrap();
         }
 
+        write!(stdout, "{}{}Or fill it from: (o) a file | (d) a DOI | (i) an ISBN | (m) a PMID | (a) a preprint | (u) a web page | (p) a pasted reference | (z) Zotero",
+            Goto(1, 3 + root_pos.1),
+            termion::clear::UntilNewline,
+        ).unwrap();
+
     },
     // Add each field in the apa data, calculate which is the longest one
     LogicState::EditState => {
@@ -80,10 +84,10 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
 
         let mut longest_field_name: usize = 0;
         // Draw each field fields
-        for (i, apa_data) in logic.apa.data.iter() {
+        for (i, field) in logic.apa.fields().iter().enumerate() {
             // Draw field names
             write!(stdout, "{}{}{}{}",
-                Goto(3, 2 + *i as u16 + root_pos.1),
+                Goto(3, 2 + i as u16 + root_pos.1),
 
                 // Add color if selected
 
@@ -91,7 +95,7 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
                 //if logic.selected == *i && logic.edit_state { format!("{}", style::Invert) } else { "".to_string() },
                 //if logic.selected == *i && logic.edit_state { format!("{}", style::Blink) } else { "".to_string() },
 
-                apa_data.0,
+                field.label,
                 style::Reset,
                 Fg(color::Reset)
                 
@@ -99,34 +103,52 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
 
             // Draw selector icon
             write!(stdout, "{}{}",
-                Goto(1, 2 + *i as u16 + root_pos.1),
-                if logic.selected == *i {">"} else {" "}
+                Goto(1, 2 + i as u16 + root_pos.1),
+                if logic.selected == i {">"} else {" "}
             ).unwrap();
 
             // If this field is bigger than longest field, update it
-            if apa_data.0.len() > longest_field_name {
-                longest_field_name = apa_data.0.len();
+            if field.label.len() > longest_field_name {
+                longest_field_name = field.label.len();
             }
         }
 
         // Draw the field's contents.
         let mut longest_field: usize = 0;
-        for (i, apa_data) in logic.apa.data.iter() {
+        let warnings = logic.apa.validate();
+        for (i, apa_data) in logic.apa.data.iter().enumerate() {
             
             write!(stdout, "{} │{}{} {}{}",
-                Goto(3 + longest_field_name as u16, 2 + *i as u16 + root_pos.1),
+                Goto(3 + longest_field_name as u16, 2 + i as u16 + root_pos.1),
 
                 // Lightup the beam of the selected field                
                 Fg(color::Reset),
                 style::Reset,
 
                 termion::clear::UntilNewline,
-                apa_data.1,
+                apa_data,
             ).unwrap();
 
+            // Tell the user what's wrong with the field, but not while they are typing in it.
+            if let Some(warning) = &warnings[i] {
+                if !(logic.edit_state && logic.selected == i) {
+                    write!(stdout, "  {}⚠ {}{}",
+                        Fg(color::Yellow),
+                        warning,
+                        Fg(color::Reset),
+                    ).unwrap();
+                }
+            } else if logic.review.contains(&logic.apa.fields()[i].id) && !(logic.edit_state && logic.selected == i) {
+                // Guessed by the import, until the user changes it.
+                write!(stdout, "  {}? check this{}",
+                    Fg(color::LightBlue),
+                    Fg(color::Reset),
+                ).unwrap();
+            }
+
             // Update the longest field if this one is longer.
-            if apa_data.1.len() > longest_field {
-                longest_field = apa_data.1.len();
+            if apa_data.len() > longest_field {
+                longest_field = apa_data.len();
             }
         }
 
@@ -179,15 +201,25 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
             style::Reset,
         ).unwrap();
 
+        // What didn't fit when the reference was imported.
+        let mut row = logic.apa.data.len() as u16 + 4 + root_pos.1;
+        if !logic.message.is_empty() {
+            write!(stdout, "{}{}{}⚠ {}{}",
+                Goto(1, row),
+                termion::clear::UntilNewline,
+                Fg(color::Yellow),
+                logic.message,
+                Fg(color::Reset),
+            ).unwrap();
+            row += 1;
+        }
+
         // Draw the "FINISHED" APA citation.
-        write!(stdout, "{}{}APA reference:{}    {}{}",
-            Goto(1, logic.apa.data.len() as u16 + 4 + root_pos.1),
+        write!(stdout, "{}{}APA reference:",
+            Goto(1, row),
             termion::clear::UntilNewline,
-
-            Goto(1, logic.apa.data.len() as u16 + 5 + root_pos.1),
-            logic.apa,
-            termion::clear::AfterCursor,
         ).unwrap();
+        draw_reference(logic, stdout, row + 1);
         
         // If in edit mode, move cursor at the end.
         if logic.edit_state {
@@ -202,11 +234,73 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
             write!(stdout, "{}", termion::cursor::Hide).unwrap()
         }
     },
+    LogicState::Prompt(kind) => {
+        write!(stdout, "{}{}{}{}-- APA 7 CLI: {} --{} (Enter) ok | (Esc) back {}{}",
+            termion::cursor::Goto(1, root_pos.1),
+            termion::color::Fg(termion::color::AnsiValue(7)),
+            termion::style::Bold,
+            termion::style::Invert,
+
+            kind.title(),
+
+            termion::cursor::Goto(1, 1 + root_pos.1),
+
+            termion::color::Bg(termion::color::Reset),
+            termion::style::Reset,
+        ).unwrap();
+
+        // Why the last try didn't work.
+        write!(stdout, "{}{}{}{}{}",
+            Goto(1, 3 + root_pos.1),
+            termion::clear::UntilNewline,
+            Fg(color::Yellow),
+            logic.message,
+            Fg(color::Reset),
+        ).unwrap();
+
+        write!(stdout, "{}{}{}: {}{}",
+            Goto(1, 2 + root_pos.1),
+            termion::clear::UntilNewline,
+            kind.label(),
+            logic.prompt,
+            termion::cursor::Show,
+        ).unwrap();
+    },
+    LogicState::Picking => {
+        write!(stdout, "{}{}{}{}{}-- APA 7 CLI: {} references found --{} (↑) up | (↓) down | (Enter) edit | (Esc) back {}{}{}",
+            termion::cursor::Goto(1, root_pos.1),
+            termion::color::Fg(termion::color::AnsiValue(7)),
+            termion::clear::AfterCursor,
+            termion::style::Bold,
+            termion::style::Invert,
+
+            logic.candidates.len(),
+
+            termion::cursor::Goto(1, 1 + root_pos.1),
+
+            termion::color::Bg(termion::color::Reset),
+            termion::style::Reset,
+            termion::cursor::Hide,
+        ).unwrap();
+
+        // Only the references around the selected one fit.
+        const SHOWN: usize = 10;
+        let width = terminal_size().unwrap().0.saturating_sub(3) as usize;
+        let first = logic.selected.saturating_sub(SHOWN - 1);
+        for (i, candidate) in logic.candidates.iter().enumerate().skip(first).take(SHOWN) {
+            let label: String = candidate.label.chars().take(width).collect();
+            write!(stdout, "{}{} {}",
+                Goto(1, 2 + (i - first) as u16 + root_pos.1),
+                if logic.selected == i {">"} else {" "},
+                label,
+            ).unwrap();
+        }
+    },
     LogicState::Result => {
         // Simple result screen.
 
         // Update the header.
-        write!(stdout, "{}{}{}{}{}-- Current APA 7 format type: {}{}{} --{} Press (Control + C) to exit.{}{}{}",
+        write!(stdout, "{}{}{}{}{}-- Current APA 7 format type: {}{}{} --{} (←) left | (→) right | (s) save to file | Press any other key to exit.{}{}{}",
             termion::cursor::Goto(1, root_pos.1),
             termion::color::Fg(termion::color::AnsiValue(7)),
             termion::clear::AfterCursor,
@@ -225,18 +319,50 @@ pub fn render(logic: &Logic, stdout: &mut RawTerminal<Stdout>, root_pos : (u16,
         ).unwrap(); 
 
         // Write the apa reference.
-        write!(stdout, "{}{}Finished APA reference:{}    {}{}",
+        write!(stdout, "{}{}Copied to the clipboard as: ",
             Goto(1, 2 + root_pos.1),
             termion::clear::UntilNewline,
+        ).unwrap();
 
+        // For each output format, print it.
+        for (i, output) in OutputFormat::list().iter().enumerate() {
+            write!(stdout, "{}{}{}, ",
+                if i == logic.selected {format!("{}", style::Underline)} else {"".to_string()},
+                output,
+                style::NoUnderline,
+            ).unwrap();
+        }
+
+        write!(stdout, "{}{}{}",
             Goto(1, 3 + root_pos.1),
-            logic.apa,
-            termion::clear::AfterCursor,
+            termion::clear::UntilNewline,
+            logic.message,
         ).unwrap();
+
+        write!(stdout, "{}{}Finished APA reference:",
+            Goto(1, 4 + root_pos.1),
+            termion::clear::UntilNewline,
+        ).unwrap();
+        draw_reference(logic, stdout, 5 + root_pos.1);
     }
 
 }
 
     // Update the terminal.
     stdout.flush().unwrap();
-}
\ No newline at end of file
+}
+
+// Draw the reference starting at the given row, wrapped to the width of the terminal.
+fn draw_reference(logic: &Logic, stdout: &mut RawTerminal<Stdout>, row: u16) {
+    const INDENT: u16 = 4;
+    let width = terminal_size().unwrap().0.saturating_sub(INDENT);
+
+    for (i, line) in Ansi.wrap(&logic.apa.reference(), width as usize).iter().enumerate() {
+        write!(stdout, "{}{}{}",
+            Goto(1 + INDENT, row + i as u16),
+            termion::clear::UntilNewline,
+            line,
+        ).unwrap();
+    }
+    write!(stdout, "{}", termion::clear::AfterCursor).unwrap();
+}